@@ -0,0 +1,199 @@
+// 缓存并发吞吐量基准测试：混合读写负载下比较分片前的单锁实现与当前的分片实现
+// 运行方式: cargo bench --bench cache_bench
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use test01::cache::{CacheConfig, SimpleCache};
+
+const KEY_SPACE: u64 = 10_000;
+const OPS_PER_THREAD: u64 = 200_000;
+const WRITE_PERCENT: u64 = 20;
+
+// 分片前的缓存实现：所有缓存项由一把互斥锁保护，只保留基准测试用到的读写和LRU、过期索引维护
+mod baseline {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    struct CacheItem {
+        value: String,
+        expiry: Option<SystemTime>,
+        size: usize,
+        last_access: u64,
+        inserted: u64,
+    }
+
+    struct CacheInner {
+        items: HashMap<String, CacheItem>,
+        lru: BTreeMap<u64, String>,
+        expiries: BTreeMap<(SystemTime, u64), String>,
+        tick: u64,
+        bytes: usize,
+    }
+
+    impl CacheInner {
+        fn next_tick(&mut self) -> u64 {
+            self.tick += 1;
+            self.tick
+        }
+
+        fn remove(&mut self, key: &str) -> Option<CacheItem> {
+            let item = self.items.remove(key)?;
+            self.lru.remove(&item.last_access);
+            if let Some(expiry) = item.expiry {
+                self.expiries.remove(&(expiry, item.inserted));
+            }
+            self.bytes -= item.size;
+            Some(item)
+        }
+
+        fn evict_to_fit(&mut self, max_entries: usize, max_bytes: usize) {
+            while self.items.len() > max_entries || self.bytes > max_bytes {
+                let Some(key) = self.lru.first_key_value().map(|(_, key)| key.clone()) else { break };
+                self.remove(&key);
+            }
+        }
+    }
+
+    pub struct SingleLockCache {
+        inner: Mutex<CacheInner>,
+        max_entries: usize,
+        max_bytes: usize,
+    }
+
+    impl SingleLockCache {
+        pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+            Self {
+                inner: Mutex::new(CacheInner {
+                    items: HashMap::new(),
+                    lru: BTreeMap::new(),
+                    expiries: BTreeMap::new(),
+                    tick: 0,
+                    bytes: 0,
+                }),
+                max_entries,
+                max_bytes,
+            }
+        }
+
+        pub fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String> {
+            let size = key.len() + value.len();
+            let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
+            let expiry = ttl.map(|seconds| SystemTime::now() + Duration::from_secs(seconds));
+
+            inner.remove(key);
+            let tick = inner.next_tick();
+            inner.lru.insert(tick, key.to_string());
+            if let Some(expiry) = expiry {
+                inner.expiries.insert((expiry, tick), key.to_string());
+            }
+            inner.bytes += size;
+            inner.items.insert(key.to_string(), CacheItem { value, expiry, size, last_access: tick, inserted: tick });
+            inner.evict_to_fit(self.max_entries, self.max_bytes);
+            Ok(())
+        }
+
+        pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+            let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
+            match inner.items.get(key) {
+                None => return Ok(None),
+                Some(item) if item.expiry.is_some_and(|expiry| expiry <= SystemTime::now()) => {
+                    inner.remove(key);
+                    return Ok(None);
+                },
+                Some(_) => {},
+            }
+
+            let tick = inner.next_tick();
+            let Some(item) = inner.items.get_mut(key) else {
+                return Ok(None);
+            };
+            let previous = std::mem::replace(&mut item.last_access, tick);
+            let value = item.value.clone();
+            inner.lru.remove(&previous);
+            inner.lru.insert(tick, key.to_string());
+            Ok(Some(value))
+        }
+    }
+}
+
+// 基准测试中两种实现共用的读写接口
+trait BenchCache: Send + Sync + 'static {
+    fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String>;
+    fn get(&self, key: &str) -> Result<Option<String>, String>;
+}
+
+impl BenchCache for baseline::SingleLockCache {
+    fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String> {
+        baseline::SingleLockCache::set(self, key, value, ttl)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        baseline::SingleLockCache::get(self, key)
+    }
+}
+
+impl BenchCache for SimpleCache {
+    fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String> {
+        SimpleCache::set(self, key, value, ttl).map_err(String::from)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        SimpleCache::get(self, key)
+    }
+}
+
+// 简单的 xorshift 随机数，避免随机数生成本身成为瓶颈
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn run<C: BenchCache>(cache: C, threads: u64) -> f64 {
+    let cache = Arc::new(cache);
+    for i in 0..KEY_SPACE {
+        cache.set(&format!("key:{}", i), format!("value:{}", i), Some(3600)).unwrap();
+    }
+
+    let started = Instant::now();
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                let mut state = 0x9E37_79B9_7F4A_7C15 ^ (t + 1);
+                for _ in 0..OPS_PER_THREAD {
+                    let random = next_random(&mut state);
+                    let key = format!("key:{}", random % KEY_SPACE);
+                    if random % 100 < WRITE_PERCENT {
+                        cache.set(&key, format!("value:{}", random), Some(3600)).unwrap();
+                    } else {
+                        let _ = cache.get(&key).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    (threads * OPS_PER_THREAD) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let threads = thread::available_parallelism().map(|n| n.get() as u64).unwrap_or(4).max(2);
+    println!("混合负载: {}% 写入, {} 个键, {} 个线程, 每线程 {} 次操作", WRITE_PERCENT, KEY_SPACE, threads, OPS_PER_THREAD);
+
+    let config = CacheConfig {
+        max_entries: KEY_SPACE as usize * 2,
+        ..CacheConfig::default()
+    };
+    let baseline = run(baseline::SingleLockCache::new(config.max_entries, config.max_bytes), threads);
+    println!("分片前单锁实现: {:>12.0} ops/s", baseline);
+    for shards in [1, 4, 16, 64] {
+        let throughput = run(SimpleCache::new(CacheConfig { shards, ..config.clone() }), threads);
+        println!("shards = {:>3}:  {:>12.0} ops/s ({:.2}x)", shards, throughput, throughput / baseline);
+    }
+}
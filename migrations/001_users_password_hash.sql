@@ -0,0 +1,3 @@
+-- 密码改为 argon2id 哈希存储，PHC 格式字符串长度约 100 个字符
+-- 旧的明文密码无需批量迁移，用户下次登录成功时会自动重新哈希
+ALTER TABLE users MODIFY COLUMN password VARCHAR(255) NOT NULL;
@@ -0,0 +1,33 @@
+-- 角色与权限
+-- 权限 '*' 表示拥有全部权限
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(64) NOT NULL PRIMARY KEY,
+    description VARCHAR(255) NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role VARCHAR(64) NOT NULL,
+    permission VARCHAR(128) NOT NULL,
+    PRIMARY KEY (role, permission),
+    CONSTRAINT fk_role_permissions_role FOREIGN KEY (role) REFERENCES roles (name) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id BIGINT UNSIGNED NOT NULL,
+    role VARCHAR(64) NOT NULL,
+    PRIMARY KEY (user_id, role),
+    CONSTRAINT fk_user_roles_role FOREIGN KEY (role) REFERENCES roles (name) ON DELETE CASCADE
+);
+
+INSERT IGNORE INTO roles (name, description) VALUES
+    ('admin', '管理员，拥有全部权限'),
+    ('user', '普通用户');
+
+INSERT IGNORE INTO role_permissions (role, permission) VALUES
+    ('admin', '*'),
+    ('user', 'users:read'),
+    ('user', 'cache:read'),
+    ('user', 'redis:read');
+
+-- 已有用户默认授予普通用户角色
+INSERT IGNORE INTO user_roles (user_id, role) SELECT id, 'user' FROM users;
@@ -0,0 +1,17 @@
+-- 服务间调用使用的API密钥
+-- 只保存密钥的SHA-256哈希，key_prefix 仅用于在列表中识别密钥
+-- scopes 为逗号分隔的权限列表，与 role_permissions.permission 取值相同
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash CHAR(64) NOT NULL,
+    owner_id BIGINT UNSIGNED NOT NULL,
+    scopes VARCHAR(1024) NOT NULL DEFAULT '',
+    expires_at DATETIME NULL,
+    last_used_at DATETIME NULL,
+    revoked_at DATETIME NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_api_keys_key_hash (key_hash),
+    KEY idx_api_keys_owner_id (owner_id)
+);
@@ -0,0 +1,2 @@
+-- 普通用户不再拥有 cache:write 权限，已执行过 002 的数据库需要收回
+DELETE FROM role_permissions WHERE role = 'user' AND permission = 'cache:write';
@@ -0,0 +1,128 @@
+use mysql::Conn;
+use mysql::prelude::Queryable;
+use serde::{Deserialize, Serialize};
+use crate::utils::time::unix_now;
+use crate::utils::token::{hash_token, random_token};
+
+// API密钥前缀，便于在日志和密钥扫描工具中识别
+const API_KEY_PREFIX: &str = "ak_";
+// 列表中展示的密钥前缀长度
+const KEY_PREFIX_LEN: usize = 11;
+// 密钥哈希到密钥ID的映射在内部缓存中的命名空间
+pub const API_KEY_CACHE_NAMESPACE: &str = "api_key";
+
+// 时间字段统一以UNIX时间戳返回
+const SQL_SELECT_API_KEY: &str = "SELECT id, name, key_prefix, owner_id, scopes, \
+     CAST(UNIX_TIMESTAMP(created_at) AS UNSIGNED), CAST(UNIX_TIMESTAMP(expires_at) AS UNSIGNED), \
+     CAST(UNIX_TIMESTAMP(last_used_at) AS UNSIGNED), CAST(UNIX_TIMESTAMP(revoked_at) AS UNSIGNED) \
+     FROM api_keys";
+const SQL_FIND_API_KEY_ID: &str = "SELECT id FROM api_keys WHERE key_hash = ?";
+const SQL_INSERT_API_KEY: &str = "INSERT INTO api_keys (name, key_prefix, key_hash, owner_id, scopes, expires_at) \
+     VALUES (?, ?, ?, ?, ?, FROM_UNIXTIME(?))";
+const SQL_REVOKE_API_KEY: &str = "UPDATE api_keys SET revoked_at = NOW() WHERE id = ? AND revoked_at IS NULL";
+// 最后使用时间精确到分钟即可，避免每个请求都写库
+const SQL_TOUCH_API_KEY: &str = "UPDATE api_keys SET last_used_at = NOW() \
+     WHERE id = ? AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL 1 MINUTE)";
+
+type ApiKeyRow = (u64, String, String, u64, String, u64, Option<u64>, Option<u64>, Option<u64>);
+
+// API密钥信息（不含密钥本身）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: u64,
+    pub name: String,
+    pub key_prefix: String,
+    pub owner_id: u64,            // 密钥所属用户，请求以该用户身份执行
+    pub scopes: Vec<String>,      // 授予的权限，与角色权限取值相同
+    pub created_at: u64,
+    pub expires_at: Option<u64>,  // 为空表示永不过期
+    pub last_used_at: Option<u64>,
+    pub revoked_at: Option<u64>,
+}
+
+impl ApiKey {
+    fn from_row(row: ApiKeyRow) -> Self {
+        let (id, name, key_prefix, owner_id, scopes, created_at, expires_at, last_used_at, revoked_at) = row;
+        Self {
+            id,
+            name,
+            key_prefix,
+            owner_id,
+            scopes: scopes.split(',').filter(|s| !s.is_empty()).map(String::from).collect(),
+            created_at,
+            expires_at,
+            last_used_at,
+            revoked_at,
+        }
+    }
+
+    // 未吊销且未过期
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > unix_now())
+    }
+}
+
+// 新创建的API密钥，明文密钥只在创建时返回一次
+#[derive(Debug, Serialize)]
+pub struct NewApiKey {
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+// 创建API密钥
+pub fn create_api_key(
+    conn: &mut Conn,
+    name: &str,
+    owner_id: u64,
+    scopes: &[String],
+    expires_at: Option<u64>,
+) -> Result<NewApiKey, mysql::Error> {
+    let key = format!("{}{}", API_KEY_PREFIX, random_token());
+    let key_prefix = &key[..KEY_PREFIX_LEN];
+    conn.exec_drop(SQL_INSERT_API_KEY, (name, key_prefix, hash_token(&key), owner_id, scopes.join(","), expires_at))?;
+
+    let api_key = ApiKey {
+        id: conn.last_insert_id(),
+        name: name.to_string(),
+        key_prefix: key_prefix.to_string(),
+        owner_id,
+        scopes: scopes.to_vec(),
+        created_at: unix_now(),
+        expires_at,
+        last_used_at: None,
+        revoked_at: None,
+    };
+    Ok(NewApiKey { key, api_key })
+}
+
+// 列出所有API密钥
+pub fn list_api_keys(conn: &mut Conn) -> Result<Vec<ApiKey>, mysql::Error> {
+    let rows: Vec<ApiKeyRow> = conn.query(format!("{} ORDER BY id", SQL_SELECT_API_KEY))?;
+    Ok(rows.into_iter().map(ApiKey::from_row).collect())
+}
+
+// 吊销API密钥，返回是否有密钥被吊销
+pub fn revoke_api_key(conn: &mut Conn, id: u64) -> Result<bool, mysql::Error> {
+    conn.exec_drop(SQL_REVOKE_API_KEY, (id,))?;
+    Ok(conn.affected_rows() > 0)
+}
+
+// 按请求携带的API密钥查找密钥ID，只比较哈希，不检查吊销和过期状态
+pub fn find_api_key_id(conn: &mut Conn, key: &str) -> Result<Option<u64>, mysql::Error> {
+    if !key.starts_with(API_KEY_PREFIX) {
+        return Ok(None);
+    }
+    conn.exec_first(SQL_FIND_API_KEY_ID, (hash_token(key),))
+}
+
+// 读取未吊销且未过期的API密钥并更新最后使用时间
+pub fn load_active_api_key(conn: &mut Conn, id: u64) -> Result<Option<ApiKey>, mysql::Error> {
+    let row: Option<ApiKeyRow> = conn.exec_first(format!("{} WHERE id = ?", SQL_SELECT_API_KEY), (id,))?;
+    match row.map(ApiKey::from_row) {
+        Some(api_key) if api_key.is_active() => {
+            conn.exec_drop(SQL_TOUCH_API_KEY, (api_key.id,))?;
+            Ok(Some(api_key))
+        },
+        _ => Ok(None),
+    }
+}
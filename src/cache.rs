@@ -1,88 +1,1016 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+// 后台清理每次持锁最多清理的缓存项数量，避免长时间阻塞读写
+const SWEEP_BATCH_SIZE: usize = 1000;
+// 每个缓存项在哈希表和索引中的额外开销估算（字节），用于统计近似内存占用
+const ENTRY_OVERHEAD_BYTES: usize = 128;
+// 键中不含 ':' 时归入的命名空间
+const DEFAULT_NAMESPACE: &str = "default";
+// 快照文件首行的格式标识，其后是内容的SHA-256校验和
+const SNAPSHOT_HEADER: &str = "simple-cache-snapshot v1";
+
+// 写入和计数操作的错误，调用方据此区分请求本身的问题和缓存内部故障
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheError {
+    TooLarge { size: usize, max_bytes: usize }, // 缓存项超过分片的字节上限
+    NotInteger(String),                         // 计数操作的当前值不是整数
+    Overflow(String),                           // 计数结果溢出
+    Internal(String),                           // 锁中毒等内部错误
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::TooLarge { size, max_bytes } => write!(f, "缓存项大小 {} 字节超过上限 {} 字节", size, max_bytes),
+            CacheError::NotInteger(key) => write!(f, "缓存项 '{}' 的值不是整数", key),
+            CacheError::Overflow(key) => write!(f, "缓存项 '{}' 的值溢出", key),
+            CacheError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<String> for CacheError {
+    fn from(msg: String) -> Self {
+        CacheError::Internal(msg)
+    }
+}
+
+impl From<CacheError> for String {
+    fn from(err: CacheError) -> Self {
+        err.to_string()
+    }
+}
+
+// 缓存容量配置，任一上限被超过时按最近最少使用（LRU）淘汰
+// 缓存按键的哈希分片，容量平均分配到各分片，LRU在分片内计算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub max_entries: usize, // 最大缓存项数量
+    pub max_bytes: usize,   // 键和值的最大总字节数
+    pub sweep_interval_secs: u64, // 后台清理过期缓存项的间隔
+    pub shards: usize,      // 分片数量，各分片独立加锁
+    pub snapshot_path: Option<String>, // 快照文件路径，不设置则不持久化
+    pub snapshot_interval_secs: u64,   // 定期写入快照的间隔
+    pub namespaces: HashMap<String, NamespaceConfig>, // 命名空间配置，键为命名空间名称
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+            sweep_interval_secs: 30,
+            shards: 16,
+            snapshot_path: None,
+            snapshot_interval_secs: 300,
+            namespaces: HashMap::new(),
+        }
+    }
+}
+
+impl CacheConfig {
+    // 不设容量上限的配置，缓存项只在过期或被删除时移除
+    // 用于吊销记录、登录锁定等安全状态：这些记录一旦被淘汰就等于失效，大量无关写入不能把它们挤出缓存
+    pub fn unbounded() -> Self {
+        Self {
+            max_entries: usize::MAX,
+            max_bytes: usize::MAX,
+            ..Self::default()
+        }
+    }
+}
+
+// 命名空间配置，命名空间是键中第一个 ':' 之前的部分
+// 容量与全局容量一样平均分配到各分片，超出时淘汰该命名空间内最久未使用的缓存项
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamespaceConfig {
+    pub default_ttl_secs: Option<u64>, // 写入时未指定过期时间则使用该值
+    pub max_entries: Option<usize>,    // 命名空间内的最大缓存项数量
+}
+
+// 命名空间信息
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespaceInfo {
+    pub name: String,
+    pub entries: usize,
+    pub default_ttl_secs: Option<u64>,
+    pub max_entries: Option<usize>,
+}
+
+// 单个分片的容量上限
+#[derive(Debug, Clone, Copy)]
+struct ShardLimits {
+    max_entries: usize,
+    max_bytes: usize,
+}
 
 // 缓存项结构
 #[derive(Debug, Clone)]
 pub struct CacheItem<T> {
     value: T,
     expiry: Option<SystemTime>,
+    stale_at: Option<SystemTime>, // 超过该时间后缓存项保留到 expiry，只在 get_or_insert_with 中作为旧值返回
+    negative: bool,               // 表示加载结果为“不存在”，get 不返回该缓存项
+    size: usize,      // 键和值占用的字节数
+    last_access: u64, // 最近一次访问的序号，用于LRU排序
+    inserted: u64,    // 写入时的序号，与过期时间一起作为过期索引的键
+    tags: Vec<String>,
+}
+
+impl<T> CacheItem<T> {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expiry.is_some_and(|expiry| expiry <= now)
+    }
+
+    fn is_stale(&self, now: SystemTime) -> bool {
+        self.stale_at.is_some_and(|stale_at| stale_at <= now)
+    }
+
+    // 是否能被 get 读取到
+    fn is_visible(&self, now: SystemTime) -> bool {
+        !self.is_expired(now) && !self.is_stale(now) && !self.negative
+    }
+}
+
+// 写入缓存项时的过期时间、标记和标签
+#[derive(Default)]
+struct WriteOptions {
+    expiry: Option<SystemTime>,
+    stale_at: Option<SystemTime>,
+    negative: bool,
+    tags: Vec<String>,
+}
+
+// 按键查找缓存项的结果
+enum Lookup {
+    Fresh(String),
+    Stale(String),
+    Negative,
+    Miss,
+}
+
+// get_or_insert_with 的加载结果，None 表示“不存在”
+type LoadResult = Result<Option<String>, String>;
+
+// get_or_insert_with 的缓存策略
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    pub ttl: Option<u64>,            // 加载结果的过期时间（秒），不设置时使用命名空间的默认过期时间
+    pub stale_while_revalidate: u64, // 过期后仍可返回旧值的时长（秒），期间在后台重新加载，需设置过期时间
+    pub negative_ttl: Option<u64>,   // 加载结果为“不存在”时的缓存时间（秒），不设置则不缓存
+}
+
+// 键及其剩余过期时间（秒），ttl 为空表示不过期
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyInfo {
+    pub key: String,
+    pub ttl: Option<u64>,
+}
+
+// 缓存统计，计数从启动或上次重置开始累计
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,                    // 键和值占用的字节数
+    pub approximate_memory_bytes: usize, // 包含索引等额外开销的近似内存占用
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,                   // 命中次数 / 读取次数，没有读取时为0
+    pub inserts: u64,                    // 写入新键的次数
+    pub overwrites: u64,                 // 覆盖未过期缓存项的次数
+    pub expirations: u64,                // 因过期被删除的缓存项数量
+    pub evictions: u64,                  // 因超出容量被淘汰的缓存项数量
+    pub namespaces: BTreeMap<String, usize>, // 各命名空间（键中第一个 ':' 之前的部分）的缓存项数量
+}
+
+// 缓存操作计数，各分片共享，不需要持锁
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    inserts: AtomicU64,
+    overwrites: AtomicU64,
+    expirations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl CacheCounters {
+    fn add(counter: &AtomicU64, n: usize) {
+        if n > 0 {
+            counter.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn reset(&self) {
+        for counter in [&self.hits, &self.misses, &self.inserts, &self.overwrites, &self.expirations, &self.evictions] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+// 键所属的命名空间
+fn namespace_of(key: &str) -> &str {
+    match key.split_once(':') {
+        Some((namespace, _)) if !namespace.is_empty() => namespace,
+        _ => DEFAULT_NAMESPACE,
+    }
+}
+
+// 缓存项的近似内存占用：键在哈希表、LRU索引和过期索引中各保存一份，每个标签在标签索引中再保存一份键
+fn footprint(key: &str, item: &CacheItem<String>) -> usize {
+    let key_copies = if item.expiry.is_some() { 2 } else { 1 };
+    let tags: usize = item.tags.iter().map(|tag| tag.len() + key.len()).sum();
+    item.size + key.len() * key_copies + tags + ENTRY_OVERHEAD_BYTES
+}
+
+// 剩余过期时间（秒），不足一秒按一秒计算
+fn remaining_secs(expiry: Option<SystemTime>, now: SystemTime) -> Option<u64> {
+    expiry.map(|expiry| expiry.duration_since(now).unwrap_or_default().as_millis().div_ceil(1000) as u64)
+}
+
+// 快照中的缓存项，过期时间为UNIX时间戳（毫秒），重启期间同样计入过期时间
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    key: String,
+    value: String,
+    expires_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+// 一个缓存分片，受各自的互斥锁保护
+#[derive(Default)]
+struct CacheInner {
+    items: HashMap<String, CacheItem<String>>,
+    lru: BTreeMap<u64, String>, // 访问序号 -> 键，序号最小的是最久未使用的
+    expiries: BTreeMap<(SystemTime, u64), String>, // 按过期时间排序的索引，只包含设置了过期时间的缓存项
+    namespaces: HashMap<String, BTreeSet<u64>>, // 命名空间 -> 其中缓存项的访问序号
+    tags: HashMap<String, HashSet<String>>,     // 标签 -> 键
+    tick: u64,
+    bytes: usize,
+    memory: usize, // 近似内存占用
+}
+
+impl CacheInner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    // 写入缓存项并更新索引，调用前需确保键不存在
+    fn insert(&mut self, key: &str, value: String, options: WriteOptions) {
+        let tick = self.next_tick();
+        self.lru.insert(tick, key.to_string());
+        if let Some(expiry) = options.expiry {
+            self.expiries.insert((expiry, tick), key.to_string());
+        }
+        let item = CacheItem {
+            size: key.len() + value.len(),
+            value,
+            expiry: options.expiry,
+            stale_at: options.stale_at,
+            negative: options.negative,
+            last_access: tick,
+            inserted: tick,
+            tags: options.tags,
+        };
+        self.bytes += item.size;
+        self.memory += footprint(key, &item);
+        self.namespaces.entry(namespace_of(key).to_string()).or_default().insert(tick);
+        for tag in &item.tags {
+            self.tags.entry(tag.clone()).or_default().insert(key.to_string());
+        }
+        self.items.insert(key.to_string(), item);
+    }
+
+    // 命中时更新访问序号，返回缓存项的值
+    fn touch(&mut self, key: &str) -> Option<String> {
+        let tick = self.next_tick();
+        let item = self.items.get_mut(key)?;
+        let previous = std::mem::replace(&mut item.last_access, tick);
+        let value = item.value.clone();
+        self.lru.remove(&previous);
+        self.lru.insert(tick, key.to_string());
+        if let Some(ticks) = self.namespaces.get_mut(namespace_of(key)) {
+            ticks.remove(&previous);
+            ticks.insert(tick);
+        }
+        Some(value)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<CacheItem<String>> {
+        let item = self.items.remove(key)?;
+        self.lru.remove(&item.last_access);
+        if let Some(expiry) = item.expiry {
+            self.expiries.remove(&(expiry, item.inserted));
+        }
+        self.bytes -= item.size;
+        self.memory -= footprint(key, &item);
+        let namespace = namespace_of(key);
+        if let Some(ticks) = self.namespaces.get_mut(namespace) {
+            ticks.remove(&item.last_access);
+            if ticks.is_empty() {
+                self.namespaces.remove(namespace);
+            }
+        }
+        for tag in &item.tags {
+            if let Some(keys) = self.tags.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
+        }
+        Some(item)
+    }
+
+    // 修改缓存项的过期时间并更新过期索引，返回 false 表示键不存在
+    // 修改后不再区分旧值，整个缓存项在新的过期时间前都可读取
+    fn set_expiry(&mut self, key: &str, expiry: Option<SystemTime>) -> bool {
+        let Some(item) = self.items.get_mut(key) else { return false };
+        let before = footprint(key, item);
+        if let Some(previous) = item.expiry {
+            self.expiries.remove(&(previous, item.inserted));
+        }
+        item.expiry = expiry;
+        item.stale_at = None;
+        if let Some(expiry) = expiry {
+            self.expiries.insert((expiry, item.inserted), key.to_string());
+        }
+        self.memory = self.memory - before + footprint(key, item);
+        true
+    }
+
+    // 按过期索引清理已过期的缓存项，最多清理 limit 个，返回清理数量
+    fn purge_expired(&mut self, now: SystemTime, limit: usize) -> usize {
+        let mut purged = 0;
+        while purged < limit {
+            let key = match self.expiries.first_key_value() {
+                Some(((expiry, _), key)) if *expiry <= now => key.clone(),
+                _ => break,
+            };
+            self.remove(&key);
+            purged += 1;
+        }
+        purged
+    }
+
+    // 淘汰最久未使用的缓存项，直到满足容量限制，返回 (过期清理数量, 淘汰数量)
+    fn evict_to_fit(&mut self, limits: ShardLimits) -> (usize, usize) {
+        let over = |inner: &Self| inner.items.len() > limits.max_entries || inner.bytes > limits.max_bytes;
+        // 优先清理已过期的缓存项
+        let expired = if over(self) { self.purge_expired(SystemTime::now(), usize::MAX) } else { 0 };
+        let mut evicted = 0;
+        while over(self) {
+            let Some(key) = self.lru.first_key_value().map(|(_, key)| key.clone()) else { break };
+            if self.remove(&key).is_some() {
+                evicted += 1;
+            }
+        }
+        (expired, evicted)
+    }
+
+    // 淘汰命名空间内最久未使用的缓存项，直到不超过 max_entries，返回淘汰数量
+    fn evict_namespace(&mut self, namespace: &str, max_entries: usize) -> usize {
+        let mut evicted = 0;
+        while let Some(ticks) = self.namespaces.get(namespace)
+            && ticks.len() > max_entries
+        {
+            let Some(key) = ticks.first().and_then(|tick| self.lru.get(tick)).cloned() else { break };
+            if self.remove(&key).is_some() {
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    // 删除命名空间内的所有缓存项，返回删除数量
+    fn remove_namespace(&mut self, namespace: &str) -> usize {
+        let keys: Vec<String> = match self.namespaces.get(namespace) {
+            Some(ticks) => ticks.iter().filter_map(|tick| self.lru.get(tick).cloned()).collect(),
+            None => return 0,
+        };
+        keys.iter().filter(|key| self.remove(key).is_some()).count()
+    }
+
+    // 删除带有指定标签的所有缓存项，返回删除数量
+    fn remove_tag(&mut self, tag: &str) -> usize {
+        let Some(keys) = self.tags.remove(tag) else { return 0 };
+        keys.iter().filter(|key| self.remove(key).is_some()).count()
+    }
+}
+
+// 单飞加载在 inflight 中的记录，释放时移除
+// 等待中的调用方被取消时保留记录，其他调用方仍在等待同一个结果
+struct InflightGuard<'a> {
+    inflight: &'a Mutex<HashMap<String, Arc<OnceCell<LoadResult>>>>,
+    key: &'a str,
+    cell: Arc<OnceCell<LoadResult>>,
+    loading: AtomicBool, // 当前调用方是否运行了 loader
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        if !self.loading.load(Ordering::Relaxed) && !self.cell.initialized() {
+            return;
+        }
+        if let Ok(mut inflight) = self.inflight.lock()
+            && inflight.get(self.key).is_some_and(|current| Arc::ptr_eq(current, &self.cell)) {
+            inflight.remove(self.key);
+        }
+    }
 }
 
 // 简单缓存实现
 pub struct SimpleCache {
-    inner: Mutex<HashMap<String, CacheItem<String>>>,
+    shards: Box<[Mutex<CacheInner>]>,
+    hasher: RandomState,
+    limits: ShardLimits,
+    config: CacheConfig,
+    counters: CacheCounters,
+    namespaces: RwLock<HashMap<String, NamespaceConfig>>,
+    inflight: Mutex<HashMap<String, Arc<OnceCell<LoadResult>>>>, // 正在加载的键
 }
 
 impl SimpleCache {
     // 创建新的缓存实例
-    pub fn new() -> Self {
+    pub fn new(config: CacheConfig) -> Self {
+        let shard_count = config.shards.max(1);
+        let limits = ShardLimits {
+            max_entries: config.max_entries.div_ceil(shard_count),
+            max_bytes: config.max_bytes.div_ceil(shard_count),
+        };
         Self {
-            inner: Mutex::new(HashMap::new()),
+            shards: (0..shard_count).map(|_| Mutex::new(CacheInner::default())).collect(),
+            hasher: RandomState::new(),
+            limits,
+            namespaces: RwLock::new(config.namespaces.clone()),
+            config,
+            counters: CacheCounters::default(),
+            inflight: Mutex::new(HashMap::new()),
         }
     }
 
-    // 设置缓存项，可选设置过期时间（秒）
-    pub fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String> {
-        let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
-        
-        let expiry = ttl.map(|seconds| {
-            SystemTime::now() + Duration::from_secs(seconds)
-        });
-        
-        inner.insert(key.to_string(), CacheItem {
-            value,
-            expiry,
-        });
-        
+    // 锁定键所在的分片
+    fn shard(&self, key: &str) -> Result<MutexGuard<'_, CacheInner>, String> {
+        let index = (self.hasher.hash_one(key) % self.shards.len() as u64) as usize;
+        self.shards[index].lock().map_err(|e| format!("Failed to lock cache: {:?}", e))
+    }
+
+    // 依次锁定所有分片
+    fn each_shard(&self) -> impl Iterator<Item = Result<MutexGuard<'_, CacheInner>, String>> {
+        self.shards.iter().map(|shard| shard.lock().map_err(|e| format!("Failed to lock cache: {:?}", e)))
+    }
+
+    // 键所属命名空间的配置，未配置时返回默认值
+    fn namespace_config(&self, key: &str) -> Result<NamespaceConfig, String> {
+        let namespaces = self.namespaces.read().map_err(|e| format!("Failed to lock cache namespaces: {:?}", e))?;
+        Ok(namespaces.get(namespace_of(key)).copied().unwrap_or_default())
+    }
+
+    // 设置缓存项，可选设置过期时间（秒），未设置时使用命名空间的默认过期时间
+    // 超出容量时淘汰最久未使用的缓存项，单个缓存项超过分片的字节上限时返回错误
+    pub fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), CacheError> {
+        self.set_tagged(key, value, ttl, &[])
+    }
+
+    // 设置带标签的缓存项，之后可通过 invalidate_tag 批量删除带有同一标签的缓存项
+    pub fn set_tagged(&self, key: &str, value: String, ttl: Option<u64>, tags: &[String]) -> Result<(), CacheError> {
+        let expiry = self.expiry_for(key, ttl)?;
+        self.store(key, value, WriteOptions { expiry, tags: tags.to_vec(), ..WriteOptions::default() })
+    }
+
+    // 按指定的过期时间写入缓存项
+    fn store(&self, key: &str, value: String, options: WriteOptions) -> Result<(), CacheError> {
+        let namespace_limit = self.admit(key, &value)?;
+        let mut inner = self.shard(key)?;
+        self.write(&mut inner, key, value, options, namespace_limit);
         Ok(())
     }
 
-    // 获取缓存项
+    // 检查缓存项大小，返回键所在命名空间在每个分片中的容量
+    fn admit(&self, key: &str, value: &str) -> Result<Option<usize>, CacheError> {
+        let size = key.len() + value.len();
+        if size > self.limits.max_bytes {
+            return Err(CacheError::TooLarge { size, max_bytes: self.limits.max_bytes });
+        }
+        Ok(self.namespace_config(key)?.max_entries.map(|max_entries| max_entries.div_ceil(self.shards.len())))
+    }
+
+    // 在已锁定的分片中写入缓存项，超出容量时淘汰
+    fn write(&self, inner: &mut CacheInner, key: &str, value: String, options: WriteOptions, namespace_limit: Option<usize>) {
+        match inner.remove(key) {
+            Some(previous) if previous.is_expired(SystemTime::now()) => CacheCounters::add(&self.counters.expirations, 1),
+            Some(_) => CacheCounters::add(&self.counters.overwrites, 1),
+            None => CacheCounters::add(&self.counters.inserts, 1),
+        }
+        inner.insert(key, value, options);
+        if let Some(max_entries) = namespace_limit {
+            CacheCounters::add(&self.counters.evictions, inner.evict_namespace(namespace_of(key), max_entries));
+        }
+        let (expired, evicted) = inner.evict_to_fit(self.limits);
+        CacheCounters::add(&self.counters.expirations, expired);
+        CacheCounters::add(&self.counters.evictions, evicted);
+    }
+
+    // 未指定过期时间时使用命名空间的默认过期时间
+    fn expiry_for(&self, key: &str, ttl: Option<u64>) -> Result<Option<SystemTime>, String> {
+        let ttl = ttl.or(self.namespace_config(key)?.default_ttl_secs);
+        Ok(ttl.map(|seconds| SystemTime::now() + Duration::from_secs(seconds)))
+    }
+
+    // 以下原子操作在分片锁内完成读取和写入，并发调用不会丢失更新
+    // 键不存在时按 ttl 写入新的缓存项（未指定时使用命名空间的默认过期时间）
+    // 修改已有的缓存项时保留其过期时间和标签，compare_and_swap 指定了 ttl 时除外
+    // 已过期的缓存项、旧值和“不存在”标记都按键不存在处理
+
+    // 将整数值加上 delta 并返回新值，键不存在时从0开始，值不是整数或结果溢出时返回错误
+    pub fn incr_by(&self, key: &str, delta: i64, ttl: Option<u64>) -> Result<i64, CacheError> {
+        let expiry = self.expiry_for(key, ttl)?;
+        // 按最长的整数检查大小
+        let namespace_limit = self.admit(key, &i64::MIN.to_string())?;
+        let mut inner = self.shard(key)?;
+        let live = self.is_live(&mut inner, key, SystemTime::now());
+        let (current, options) = match inner.items.get(key).filter(|_| live) {
+            Some(item) => {
+                let current = item.value.parse::<i64>()
+                    .map_err(|_| CacheError::NotInteger(key.to_string()))?;
+                (current, WriteOptions { expiry: item.expiry, stale_at: item.stale_at, tags: item.tags.clone(), ..WriteOptions::default() })
+            },
+            _ => (0, WriteOptions { expiry, ..WriteOptions::default() }),
+        };
+        let value = current.checked_add(delta).ok_or_else(|| CacheError::Overflow(key.to_string()))?;
+        self.write(&mut inner, key, value.to_string(), options, namespace_limit);
+        Ok(value)
+    }
+
+    // 将整数值减去 delta 并返回新值，语义同 incr_by
+    pub fn decr_by(&self, key: &str, delta: i64, ttl: Option<u64>) -> Result<i64, CacheError> {
+        let delta = delta.checked_neg().ok_or_else(|| CacheError::Overflow(key.to_string()))?;
+        self.incr_by(key, delta, ttl)
+    }
+
+    // 键不存在时写入缓存项，返回是否写入
+    pub fn set_if_absent(&self, key: &str, value: String, ttl: Option<u64>) -> Result<bool, CacheError> {
+        let expiry = self.expiry_for(key, ttl)?;
+        let namespace_limit = self.admit(key, &value)?;
+        let mut inner = self.shard(key)?;
+        if self.is_live(&mut inner, key, SystemTime::now()) {
+            return Ok(false);
+        }
+        self.write(&mut inner, key, value, WriteOptions { expiry, ..WriteOptions::default() }, namespace_limit);
+        Ok(true)
+    }
+
+    // 当前值等于 expected 时替换为 value，返回是否替换；键不存在时不写入
+    // ttl 为空时保留原有的过期时间，否则从当前时间重新计算
+    pub fn compare_and_swap(&self, key: &str, expected: &str, value: String, ttl: Option<u64>) -> Result<bool, CacheError> {
+        let namespace_limit = self.admit(key, &value)?;
+        let mut inner = self.shard(key)?;
+        if !self.is_live(&mut inner, key, SystemTime::now()) {
+            return Ok(false);
+        }
+        let Some(item) = inner.items.get(key).filter(|item| item.value == expected) else { return Ok(false) };
+        let options = match ttl {
+            Some(ttl) => WriteOptions {
+                expiry: Some(SystemTime::now() + Duration::from_secs(ttl)),
+                tags: item.tags.clone(),
+                ..WriteOptions::default()
+            },
+            None => WriteOptions { expiry: item.expiry, stale_at: item.stale_at, tags: item.tags.clone(), ..WriteOptions::default() },
+        };
+        self.write(&mut inner, key, value, options, namespace_limit);
+        Ok(true)
+    }
+
+    // 获取缓存项，已过期的缓存项在读取时删除
+    // get_or_insert_with 写入的旧值和“不存在”标记不会被返回
     pub fn get(&self, key: &str) -> Result<Option<String>, String> {
-        let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
-        
-        // 清理过期的项目
-        self.cleanup_expired(&mut inner);
-        
-        if let Some(item) = inner.get(key) {
-            Ok(Some(item.value.clone()))
-        } else {
-            Ok(None)
+        match self.lookup(key)? {
+            Lookup::Fresh(value) => {
+                CacheCounters::add(&self.counters.hits, 1);
+                Ok(Some(value))
+            },
+            _ => {
+                CacheCounters::add(&self.counters.misses, 1);
+                Ok(None)
+            },
+        }
+    }
+
+    // 查找缓存项，已过期的缓存项在读取时删除，命中时更新访问序号
+    fn lookup(&self, key: &str) -> Result<Lookup, String> {
+        let mut inner = self.shard(key)?;
+        let now = SystemTime::now();
+        let (stale, negative) = match inner.items.get(key) {
+            None => return Ok(Lookup::Miss),
+            Some(item) if item.is_expired(now) => {
+                inner.remove(key);
+                CacheCounters::add(&self.counters.expirations, 1);
+                return Ok(Lookup::Miss);
+            },
+            Some(item) => (item.is_stale(now), item.negative),
+        };
+
+        let Some(value) = inner.touch(key) else { return Ok(Lookup::Miss) };
+        Ok(match (negative, stale) {
+            (true, _) => Lookup::Negative,
+            (false, true) => Lookup::Stale(value),
+            (false, false) => Lookup::Fresh(value),
+        })
+    }
+
+    // 读取缓存项，未命中时调用 loader 加载并写入缓存
+    // 同一个键同时只运行一个 loader，其他调用方等待同一个结果；loader 出错时结果不缓存，错误返回给所有等待的调用方
+    // 设置了 stale_while_revalidate 时，过期不久的旧值会直接返回，同时在后台重新加载
+    // 设置了 negative_ttl 时，“不存在”的结果也会缓存，期间不再调用 loader
+    pub async fn get_or_insert_with<F, Fut>(self: &Arc<Self>, key: &str, options: LoadOptions, loader: F) -> LoadResult
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = LoadResult> + Send + 'static,
+    {
+        match self.lookup(key)? {
+            Lookup::Fresh(value) => {
+                CacheCounters::add(&self.counters.hits, 1);
+                return Ok(Some(value));
+            },
+            Lookup::Negative => {
+                CacheCounters::add(&self.counters.hits, 1);
+                return Ok(None);
+            },
+            Lookup::Stale(value) => {
+                CacheCounters::add(&self.counters.hits, 1);
+                if !self.is_loading(key)? {
+                    let cache = Arc::clone(self);
+                    let key = key.to_string();
+                    tokio::spawn(async move {
+                        if let Err(e) = cache.load(&key, options, loader).await {
+                            warn!("后台刷新缓存项 '{}' 失败: {}", key, e);
+                        }
+                    });
+                }
+                return Ok(Some(value));
+            },
+            Lookup::Miss => CacheCounters::add(&self.counters.misses, 1),
+        }
+        self.load(key, options, loader).await
+    }
+
+    fn is_loading(&self, key: &str) -> Result<bool, String> {
+        let inflight = self.inflight.lock().map_err(|e| format!("Failed to lock cache loaders: {:?}", e))?;
+        Ok(inflight.contains_key(key))
+    }
+
+    // 单飞加载：加入该键正在进行的加载，没有时由当前调用方运行 loader
+    async fn load<F, Fut>(&self, key: &str, options: LoadOptions, loader: F) -> LoadResult
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = LoadResult>,
+    {
+        let cell = {
+            let mut inflight = self.inflight.lock().map_err(|e| format!("Failed to lock cache loaders: {:?}", e))?;
+            Arc::clone(inflight.entry(key.to_string()).or_default())
+        };
+        // 加载完成后移除，之后的调用方直接读取缓存；运行 loader 的调用方被取消时同样移除
+        let guard = InflightGuard { inflight: &self.inflight, key, cell, loading: AtomicBool::new(false) };
+
+        guard.cell.get_or_init(|| async {
+            guard.loading.store(true, Ordering::Relaxed);
+            let result = loader().await;
+            if let Err(e) = self.store_loaded(key, options, &result) {
+                warn!("写入加载结果失败: {}, key={}", e, key);
+            }
+            result
+        }).await.clone()
+    }
+
+    fn store_loaded(&self, key: &str, options: LoadOptions, result: &LoadResult) -> Result<(), String> {
+        let now = SystemTime::now();
+        match result {
+            Ok(Some(value)) => {
+                let ttl = options.ttl.or(self.namespace_config(key)?.default_ttl_secs);
+                let write = match ttl {
+                    Some(ttl) if options.stale_while_revalidate > 0 => {
+                        let stale_at = now + Duration::from_secs(ttl);
+                        WriteOptions {
+                            expiry: Some(stale_at + Duration::from_secs(options.stale_while_revalidate)),
+                            stale_at: Some(stale_at),
+                            ..WriteOptions::default()
+                        }
+                    },
+                    _ => WriteOptions { expiry: ttl.map(|ttl| now + Duration::from_secs(ttl)), ..WriteOptions::default() },
+                };
+                self.store(key, value.clone(), write).map_err(String::from)
+            },
+            Ok(None) => match options.negative_ttl {
+                Some(ttl) => self.store(key, String::new(), WriteOptions {
+                    expiry: Some(now + Duration::from_secs(ttl)),
+                    negative: true,
+                    ..WriteOptions::default()
+                }).map_err(String::from),
+                None => self.remove(key).map(|_| ()),
+            },
+            // 出错时保留已有的旧值
+            Err(_) => Ok(()),
+        }
+    }
+
+    // 以JSON序列化后写入缓存，可缓存任意实现了 Serialize 的值
+    pub fn set_typed<T: Serialize + ?Sized>(&self, key: &str, value: &T, ttl: Option<u64>) -> Result<(), String> {
+        let raw = serde_json::to_string(value).map_err(|e| format!("序列化缓存项 '{}' 失败: {}", key, e))?;
+        self.set(key, raw, ttl).map_err(String::from)
+    }
+
+    // 读取 set_typed 写入的缓存项并反序列化，内容与目标类型不匹配时返回错误
+    pub fn get_typed<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key)? {
+            Some(raw) => serde_json::from_str(&raw)
+                .map(Some)
+                .map_err(|e| format!("反序列化缓存项 '{}' 失败: {}", key, e)),
+            None => Ok(None),
         }
     }
 
     // 删除缓存项
     pub fn remove(&self, key: &str) -> Result<bool, String> {
-        let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
+        let mut inner = self.shard(key)?;
         Ok(inner.remove(key).is_some())
     }
 
-    // 清理过期的缓存项
-    fn cleanup_expired(&self, inner: &mut HashMap<String, CacheItem<String>>) {
+    // 键存在且能被 get 读取到时返回 true，已过期的缓存项顺便删除
+    fn is_live(&self, inner: &mut CacheInner, key: &str, now: SystemTime) -> bool {
+        match inner.items.get(key) {
+            Some(item) if item.is_expired(now) => {
+                inner.remove(key);
+                CacheCounters::add(&self.counters.expirations, 1);
+                false
+            },
+            Some(item) => item.is_visible(now),
+            None => false,
+        }
+    }
+
+    // 获取缓存项的剩余过期时间（秒），外层 None 表示键不存在，内层 None 表示不过期
+    pub fn ttl(&self, key: &str) -> Result<Option<Option<u64>>, String> {
+        let now = SystemTime::now();
+        let mut inner = self.shard(key)?;
+        if !self.is_live(&mut inner, key, now) {
+            return Ok(None);
+        }
+        Ok(inner.items.get(key).map(|item| remaining_secs(item.expiry, now)))
+    }
+
+    // 重新设置已有缓存项的过期时间（秒），返回 false 表示键不存在
+    pub fn expire(&self, key: &str, ttl: u64) -> Result<bool, String> {
+        self.update_expiry(key, Some(SystemTime::now() + Duration::from_secs(ttl)))
+    }
+
+    // 移除已有缓存项的过期时间，返回 false 表示键不存在
+    pub fn persist(&self, key: &str) -> Result<bool, String> {
+        self.update_expiry(key, None)
+    }
+
+    fn update_expiry(&self, key: &str, expiry: Option<SystemTime>) -> Result<bool, String> {
+        let mut inner = self.shard(key)?;
+        if !self.is_live(&mut inner, key, SystemTime::now()) {
+            return Ok(false);
+        }
+        Ok(inner.set_expiry(key, expiry))
+    }
+
+    // 按字典序列出满足 filter 且大于 cursor 的前 limit 个键，以最后一个键作为下一页的 cursor
+    // 需要遍历所有缓存项，只适合管理接口使用；不包含旧值和“不存在”标记，不影响LRU顺序
+    pub fn scan(&self, cursor: Option<&str>, limit: usize, filter: impl Fn(&str) -> bool) -> Result<Vec<KeyInfo>, String> {
         let now = SystemTime::now();
-        inner.retain(|_, item| {
-            if let Some(expiry) = item.expiry {
-                match expiry.duration_since(now) {
-                    Ok(_) => true,  // 未过期
-                    Err(_) => false, // 已过期
+        let mut page: BTreeMap<String, Option<u64>> = BTreeMap::new();
+        for inner in self.each_shard() {
+            let inner = inner?;
+            for (key, item) in &inner.items {
+                if cursor.is_some_and(|cursor| key.as_str() <= cursor)
+                    || page.len() >= limit && page.last_key_value().is_some_and(|(last, _)| key >= last)
+                    || !item.is_visible(now)
+                    || !filter(key)
+                {
+                    continue;
+                }
+                page.insert(key.clone(), remaining_secs(item.expiry, now));
+                if page.len() > limit {
+                    page.pop_last();
                 }
-            } else {
-                true  // 永不过期
             }
-        });
+        }
+        Ok(page.into_iter().map(|(key, ttl)| KeyInfo { key, ttl }).collect())
+    }
+
+    // 清理所有已过期的缓存项，分批持锁，返回清理数量
+    pub fn purge_expired(&self) -> Result<usize, String> {
+        let now = SystemTime::now();
+        let mut total = 0;
+        for shard in self.shards.iter() {
+            loop {
+                let mut inner = shard.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
+                let purged = inner.purge_expired(now, SWEEP_BATCH_SIZE);
+                total += purged;
+                CacheCounters::add(&self.counters.expirations, purged);
+                if purged < SWEEP_BATCH_SIZE {
+                    break;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    // 获取缓存统计
+    pub fn stats(&self) -> Result<CacheStats, String> {
+        let now = SystemTime::now();
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let (hits, misses) = (load(&self.counters.hits), load(&self.counters.misses));
+        let mut stats = CacheStats {
+            entries: 0,
+            bytes: 0,
+            approximate_memory_bytes: 0,
+            max_entries: self.config.max_entries,
+            max_bytes: self.config.max_bytes,
+            hits,
+            misses,
+            hit_rate: if hits + misses == 0 { 0.0 } else { hits as f64 / (hits + misses) as f64 },
+            inserts: load(&self.counters.inserts),
+            overwrites: load(&self.counters.overwrites),
+            expirations: 0,
+            evictions: load(&self.counters.evictions),
+            namespaces: BTreeMap::new(),
+        };
+        for inner in self.each_shard() {
+            let mut inner = inner?;
+            CacheCounters::add(&self.counters.expirations, inner.purge_expired(now, usize::MAX));
+            stats.entries += inner.items.len();
+            stats.bytes += inner.bytes;
+            stats.approximate_memory_bytes += inner.memory;
+            for (namespace, ticks) in &inner.namespaces {
+                *stats.namespaces.entry(namespace.clone()).or_insert(0) += ticks.len();
+            }
+        }
+        stats.expirations = load(&self.counters.expirations);
+        Ok(stats)
     }
 
-    // 获取缓存项数量
-    pub fn len(&self) -> Result<usize, String> {
-        let inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
-        Ok(inner.len())
+    // 重置命中、写入、过期和淘汰计数，不影响缓存内容
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    // 将未过期的缓存项写入快照文件，先写临时文件再重命名，返回写入的缓存项数量
+    pub fn snapshot(&self, path: &Path) -> Result<usize, String> {
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        for inner in self.each_shard() {
+            let inner = inner?;
+            // 按LRU顺序写入，恢复时各分片内的访问顺序保持不变
+            for key in inner.lru.values() {
+                let Some(item) = inner.items.get(key) else { continue };
+                // 旧值和“不存在”标记只在短时间内有意义，不写入快照
+                if !item.is_visible(now) {
+                    continue;
+                }
+                entries.push(SnapshotEntry {
+                    key: key.clone(),
+                    value: item.value.clone(),
+                    expires_at_ms: item.stale_at.or(item.expiry).map(unix_millis),
+                    tags: item.tags.clone(),
+                });
+            }
+        }
+
+        let body = serde_json::to_vec(&entries).map_err(|e| format!("序列化缓存快照失败: {}", e))?;
+        let checksum = format!("{:x}", Sha256::digest(&body));
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("创建快照目录 '{}' 失败: {}", dir.display(), e))?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let write = || -> std::io::Result<()> {
+            let mut file = open_private(&tmp_path)?;
+            writeln!(file, "{} {}", SNAPSHOT_HEADER, checksum)?;
+            file.write_all(&body)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)
+        };
+        write().map_err(|e| format!("写入缓存快照 '{}' 失败: {}", path.display(), e))?;
+        Ok(entries.len())
+    }
+
+    // 从快照文件恢复缓存项，文件不存在时不做任何事，校验失败时返回错误且不写入任何缓存项
+    pub fn restore(&self, path: &Path) -> Result<usize, String> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("读取缓存快照 '{}' 失败: {}", path.display(), e)),
+        };
+        let entries = parse_snapshot(&data).map_err(|e| format!("缓存快照 '{}' 无效: {}", path.display(), e))?;
+
+        let now = SystemTime::now();
+        let mut restored = 0;
+        for entry in entries {
+            let expiry = entry.expires_at_ms.map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
+            if expiry.is_some_and(|expiry| expiry <= now) {
+                continue;
+            }
+            match self.store(&entry.key, entry.value, WriteOptions { expiry, tags: entry.tags, ..WriteOptions::default() }) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("跳过无法恢复的缓存项 '{}': {}", entry.key, e),
+            }
+        }
+        Ok(restored)
+    }
+
+    // 设置命名空间的默认过期时间和容量，容量缩小时立即淘汰超出的缓存项
+    // 默认过期时间只影响之后写入的缓存项
+    pub fn define_namespace(&self, name: &str, config: NamespaceConfig) -> Result<(), String> {
+        self.namespaces
+            .write()
+            .map_err(|e| format!("Failed to lock cache namespaces: {:?}", e))?
+            .insert(name.to_string(), config);
+        if let Some(max_entries) = config.max_entries {
+            let limit = max_entries.div_ceil(self.shards.len());
+            for inner in self.each_shard() {
+                CacheCounters::add(&self.counters.evictions, inner?.evict_namespace(name, limit));
+            }
+        }
+        Ok(())
+    }
+
+    // 列出已配置或当前有缓存项的命名空间
+    pub fn namespaces(&self) -> Result<Vec<NamespaceInfo>, String> {
+        let now = SystemTime::now();
+        let mut entries: BTreeMap<String, usize> = BTreeMap::new();
+        for inner in self.each_shard() {
+            let mut inner = inner?;
+            CacheCounters::add(&self.counters.expirations, inner.purge_expired(now, usize::MAX));
+            for (namespace, ticks) in &inner.namespaces {
+                *entries.entry(namespace.clone()).or_insert(0) += ticks.len();
+            }
+        }
+
+        let configs = self.namespaces.read().map_err(|e| format!("Failed to lock cache namespaces: {:?}", e))?;
+        for name in configs.keys() {
+            entries.entry(name.clone()).or_insert(0);
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(name, entries)| {
+                let config = configs.get(&name).copied().unwrap_or_default();
+                NamespaceInfo {
+                    name,
+                    entries,
+                    default_ttl_secs: config.default_ttl_secs,
+                    max_entries: config.max_entries,
+                }
+            })
+            .collect())
+    }
+
+    // 删除命名空间内的所有缓存项，返回删除数量
+    pub fn clear_namespace(&self, name: &str) -> Result<usize, String> {
+        let mut removed = 0;
+        for inner in self.each_shard() {
+            removed += inner?.remove_namespace(name);
+        }
+        Ok(removed)
+    }
+
+    // 删除带有指定标签的所有缓存项，返回删除数量
+    pub fn invalidate_tag(&self, tag: &str) -> Result<usize, String> {
+        let mut removed = 0;
+        for inner in self.each_shard() {
+            removed += inner?.remove_tag(tag);
+        }
+        Ok(removed)
     }
 
     // 清空缓存
     pub fn clear(&self) -> Result<(), String> {
-        let mut inner = self.inner.lock().map_err(|e| format!("Failed to lock cache: {:?}", e))?;
-        inner.clear();
+        for inner in self.each_shard() {
+            *inner? = CacheInner::default();
+        }
         Ok(())
     }
 }
@@ -90,7 +1018,601 @@ impl SimpleCache {
 // 缓存类型别名，方便使用
 pub type Cache = Arc<SimpleCache>;
 
-// 初始化缓存
-pub fn init_cache() -> Cache {
-    Arc::new(SimpleCache::new())
-}
\ No newline at end of file
+// 初始化缓存，配置了快照文件时从快照恢复，快照损坏时记录警告并以空缓存启动
+pub fn init_cache(config: &CacheConfig) -> Cache {
+    let cache = Arc::new(SimpleCache::new(config.clone()));
+    if let Some(path) = &config.snapshot_path {
+        match cache.restore(Path::new(path)) {
+            Ok(0) => {},
+            Ok(restored) => info!("已从快照恢复 {} 个缓存项", restored),
+            Err(e) => warn!("{}，忽略该快照", e),
+        }
+    }
+    cache
+}
+
+// 启动后台任务，定期写入缓存快照，需在tokio运行时中调用
+// shutdown 变为 true 后任务在当前快照写完后退出，等待返回的 JoinHandle 后再写最终快照，避免两次写入争用同一个临时文件
+pub fn spawn_snapshotter(
+    cache: Cache,
+    path: PathBuf,
+    interval: Duration,
+    mut shutdown: tokio::sync::watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // 第一次 tick 立即完成，跳过以免启动时重复写入刚恢复的快照
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {},
+                // 发送端被丢弃时同样退出
+                changed = shutdown.changed() => {
+                    if changed.is_err() || *shutdown.borrow() {
+                        break;
+                    }
+                    continue;
+                },
+            }
+            let cache = cache.clone();
+            let path = path.clone();
+            match tokio::task::spawn_blocking(move || cache.snapshot(&path)).await {
+                Ok(Ok(count)) => debug!("已写入缓存快照，共 {} 个缓存项", count),
+                Ok(Err(e)) => error!("{}", e),
+                Err(e) => error!("写入缓存快照的任务异常退出: {}", e),
+            }
+        }
+    })
+}
+
+// 启动后台任务，定期清理过期的缓存项，需在tokio运行时中调用
+pub fn spawn_sweeper(cache: Cache, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match cache.purge_expired() {
+                Ok(0) => {},
+                Ok(purged) => debug!("已清理 {} 个过期缓存项", purged),
+                Err(e) => error!("清理过期缓存项失败: {}", e),
+            }
+        }
+    })
+}
+
+// 校验快照首行的格式标识和校验和，并解析缓存项
+fn parse_snapshot(data: &[u8]) -> Result<Vec<SnapshotEntry>, String> {
+    let newline = data.iter().position(|&b| b == b'\n').ok_or("缺少文件头")?;
+    let header = std::str::from_utf8(&data[..newline]).map_err(|_| "文件头不是有效的UTF-8")?;
+    let checksum = header
+        .strip_prefix(SNAPSHOT_HEADER)
+        .and_then(|rest| rest.strip_prefix(' '))
+        .ok_or("不支持的快照格式")?;
+    let body = &data[newline + 1..];
+    if format!("{:x}", Sha256::digest(body)) != checksum {
+        return Err(String::from("校验和不匹配"));
+    }
+    serde_json::from_slice(body).map_err(|e| e.to_string())
+}
+
+// 快照可能包含令牌等敏感数据，仅允许当前用户读写
+fn open_private(path: &Path) -> std::io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cache() -> Cache {
+        Arc::new(SimpleCache::new(CacheConfig::default()))
+    }
+
+    #[tokio::test]
+    async fn cancelled_loader_does_not_block_later_loads() {
+        let cache = test_cache();
+        let pending = cache.get_or_insert_with("user:1", LoadOptions::default(), futures::future::pending);
+        assert!(tokio::time::timeout(Duration::from_millis(10), pending).await.is_err());
+        assert!(!cache.is_loading("user:1").unwrap());
+
+        let loaded = cache.get_or_insert_with("user:1", LoadOptions::default(), || async { Ok(Some(String::from("alice"))) }).await;
+        assert_eq!(loaded, Ok(Some(String::from("alice"))));
+        assert_eq!(cache.get("user:1"), Ok(Some(String::from("alice"))));
+    }
+
+    #[tokio::test]
+    async fn snapshotter_stops_on_shutdown() {
+        let (shutdown, receiver) = tokio::sync::watch::channel(false);
+        let path = std::env::temp_dir().join(format!("cache-snapshotter-{}.json", std::process::id()));
+        let handle = spawn_snapshotter(test_cache(), path, Duration::from_secs(3600), receiver);
+        shutdown.send(true).unwrap();
+        assert!(tokio::time::timeout(Duration::from_secs(1), handle).await.is_ok());
+    }
+
+    #[test]
+    fn restore_round_trips_a_snapshot() {
+        let source = test_cache();
+        source.set("user:1", String::from("alice"), None).unwrap();
+        source.set_tagged("user:2", String::from("bob"), Some(3600), &[String::from("team")]).unwrap();
+        let path = std::env::temp_dir().join(format!("cache-round-trip-{}.json", std::process::id()));
+        assert_eq!(source.snapshot(&path), Ok(2));
+
+        let restored = test_cache();
+        assert_eq!(restored.restore(&path), Ok(2));
+        assert_eq!(restored.get("user:1"), Ok(Some(String::from("alice"))));
+        assert!(restored.ttl("user:2").unwrap().unwrap().is_some_and(|ttl| ttl <= 3600));
+        assert_eq!(restored.invalidate_tag("team"), Ok(1));
+        fs::remove_file(&path).unwrap();
+
+        // 快照文件不存在时按空缓存处理
+        assert_eq!(test_cache().restore(&path), Ok(0));
+    }
+
+    #[test]
+    fn restore_rejects_corrupted_snapshots_and_leaves_the_cache_empty() {
+        let source = test_cache();
+        for i in 0..10 {
+            source.set(&format!("user:{}", i), format!("value-{}", i), None).unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("cache-corrupted-{}.json", std::process::id()));
+        source.snapshot(&path).unwrap();
+        let valid = fs::read(&path).unwrap();
+        let newline = valid.iter().position(|&b| b == b'\n').unwrap();
+
+        let mut wrong_checksum = valid.clone();
+        wrong_checksum[newline - 1] = if valid[newline - 1] == b'0' { b'1' } else { b'0' };
+        let mut tampered_body = valid.clone();
+        let value_at = newline + valid[newline..].windows(7).position(|w| w == b"value-3").unwrap();
+        tampered_body[value_at + 6] = b'9';
+        let wrong_version = String::from_utf8(valid.clone()).unwrap().replacen(SNAPSHOT_HEADER, "simple-cache-snapshot v0", 1).into_bytes();
+
+        for (case, data) in [
+            ("wrong checksum", wrong_checksum),
+            ("tampered body", tampered_body),
+            ("unknown format", wrong_version),
+            ("truncated body", valid[..valid.len() - 10].to_vec()),
+            ("truncated header", valid[..newline].to_vec()),
+        ] {
+            fs::write(&path, data).unwrap();
+            let cache = test_cache();
+            assert!(cache.restore(&path).is_err(), "{}", case);
+            assert_eq!(cache.stats().unwrap().entries, 0, "{}", case);
+
+            let config = CacheConfig { snapshot_path: Some(path.to_string_lossy().into_owned()), ..CacheConfig::default() };
+            assert_eq!(init_cache(&config).stats().unwrap().entries, 0, "{}", case);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    // 单分片缓存，淘汰顺序与键的分布无关
+    fn single_shard(config: CacheConfig) -> Cache {
+        Arc::new(SimpleCache::new(CacheConfig { shards: 1, ..config }))
+    }
+
+    // 将缓存项的过期时间改为已过去的时间，不等待真实时间流逝
+    fn expire_now(cache: &Cache, key: &str) {
+        assert!(cache.shard(key).unwrap().set_expiry(key, Some(SystemTime::now() - Duration::from_secs(1))));
+    }
+
+    fn present(cache: &Cache, keys: &[&str]) -> Vec<String> {
+        keys.iter().filter(|key| cache.scan(None, usize::MAX, |k| k == **key).unwrap().len() == 1).map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_at_max_entries() {
+        let cache = single_shard(CacheConfig { max_entries: 3, ..CacheConfig::default() });
+        for key in ["a", "b", "c"] {
+            cache.set(key, String::from("1"), None).unwrap();
+        }
+        // 读取 a 后，b 成为最久未使用的缓存项
+        cache.get("a").unwrap();
+        cache.set("d", String::from("1"), None).unwrap();
+        assert_eq!(present(&cache, &["a", "b", "c", "d"]), vec!["a", "c", "d"]);
+
+        // 覆盖写入同样算作访问
+        cache.set("c", String::from("2"), None).unwrap();
+        cache.set("e", String::from("1"), None).unwrap();
+        assert_eq!(present(&cache, &["a", "c", "d", "e"]), vec!["c", "d", "e"]);
+        assert_eq!(cache.stats().unwrap().evictions, 2);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_at_max_bytes() {
+        // 每个缓存项的键和值共10字节
+        let cache = single_shard(CacheConfig { max_bytes: 30, ..CacheConfig::default() });
+        for key in ["k1", "k2", "k3"] {
+            cache.set(key, String::from("12345678"), None).unwrap();
+        }
+        assert_eq!(cache.stats().unwrap().bytes, 30);
+        cache.get("k1").unwrap();
+        cache.set("k4", String::from("12345678"), None).unwrap();
+        assert_eq!(present(&cache, &["k1", "k2", "k3", "k4"]), vec!["k1", "k3", "k4"]);
+
+        // 一个较大的缓存项可以淘汰多个缓存项
+        cache.set("k5", String::from("1234567890123456"), None).unwrap();
+        assert_eq!(present(&cache, &["k1", "k3", "k4", "k5"]), vec!["k4", "k5"]);
+        assert!(cache.stats().unwrap().bytes <= 30);
+
+        // 超过上限的缓存项直接拒绝，不淘汰已有的缓存项
+        assert_eq!(cache.set("k6", "x".repeat(29), None), Err(CacheError::TooLarge { size: 31, max_bytes: 30 }));
+        assert_eq!(present(&cache, &["k4", "k5"]), vec!["k4", "k5"]);
+    }
+
+    #[test]
+    fn expired_entries_are_removed_before_evicting_live_ones() {
+        let cache = single_shard(CacheConfig { max_entries: 3, ..CacheConfig::default() });
+        for key in ["a", "b", "c"] {
+            cache.set(key, String::from("1"), Some(3600)).unwrap();
+        }
+        expire_now(&cache, "c");
+        cache.set("d", String::from("1"), None).unwrap();
+        assert_eq!(present(&cache, &["a", "b", "c", "d"]), vec!["a", "b", "d"]);
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.expirations, stats.evictions), (1, 0));
+    }
+
+    #[test]
+    fn namespace_limits_only_evict_within_the_namespace() {
+        let namespaces = HashMap::from([(String::from("session"), NamespaceConfig { max_entries: Some(2), ..NamespaceConfig::default() })]);
+        let cache = single_shard(CacheConfig { namespaces, ..CacheConfig::default() });
+        for i in 1..=3 {
+            cache.set(&format!("user:{}", i), String::from("1"), None).unwrap();
+        }
+        cache.set("session:1", String::from("1"), None).unwrap();
+        cache.set("session:2", String::from("1"), None).unwrap();
+        cache.get("session:1").unwrap();
+        cache.set("session:3", String::from("1"), None).unwrap();
+
+        assert_eq!(present(&cache, &["session:1", "session:2", "session:3"]), vec!["session:1", "session:3"]);
+        assert_eq!(present(&cache, &["user:1", "user:2", "user:3"]).len(), 3);
+
+        // 缩小命名空间容量时立即淘汰超出的缓存项
+        cache.define_namespace("user", NamespaceConfig { max_entries: Some(1), ..NamespaceConfig::default() }).unwrap();
+        assert_eq!(present(&cache, &["user:1", "user:2", "user:3"]), vec!["user:3"]);
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.namespaces.get("user"), Some(&1));
+        assert_eq!(stats.evictions, 3);
+    }
+
+    #[test]
+    fn invalidate_tag_removes_tagged_entries_across_shards() {
+        let cache = test_cache();
+        let team = [String::from("team:1")];
+        for i in 0..50 {
+            cache.set_tagged(&format!("user:{}", i), String::from("1"), None, &team).unwrap();
+        }
+        cache.set_tagged("user:other", String::from("1"), None, &[String::from("team:2")]).unwrap();
+        // 不带标签覆盖写入后，缓存项不再属于原标签
+        cache.set("user:0", String::from("2"), None).unwrap();
+
+        assert_eq!(cache.invalidate_tag("team:1"), Ok(49));
+        assert_eq!(cache.get("user:1"), Ok(None));
+        assert_eq!(cache.get("user:0"), Ok(Some(String::from("2"))));
+        assert_eq!(cache.get("user:other"), Ok(Some(String::from("1"))));
+        assert_eq!(cache.invalidate_tag("team:1"), Ok(0));
+    }
+
+    #[test]
+    fn clear_namespace_only_removes_that_namespace() {
+        let cache = test_cache();
+        for i in 0..50 {
+            cache.set(&format!("user:{}", i), String::from("1"), None).unwrap();
+            cache.set(&format!("session:{}", i), String::from("1"), None).unwrap();
+        }
+        cache.set("plain", String::from("1"), None).unwrap();
+
+        assert_eq!(cache.clear_namespace("user"), Ok(50));
+        let namespaces = cache.stats().unwrap().namespaces;
+        assert_eq!(namespaces.get("user"), None);
+        assert_eq!(namespaces.get("session"), Some(&50));
+
+        // 不含 ':' 的键属于默认命名空间
+        assert_eq!(cache.clear_namespace(DEFAULT_NAMESPACE), Ok(1));
+        assert_eq!(cache.get("plain"), Ok(None));
+        assert_eq!(cache.clear_namespace("missing"), Ok(0));
+    }
+
+    #[test]
+    fn namespace_default_ttl_applies_to_later_writes_without_ttl() {
+        let cache = test_cache();
+        cache.set("session:old", String::from("1"), None).unwrap();
+        cache.define_namespace("session", NamespaceConfig { default_ttl_secs: Some(60), ..NamespaceConfig::default() }).unwrap();
+
+        cache.set("session:1", String::from("1"), None).unwrap();
+        cache.set("session:2", String::from("1"), Some(10)).unwrap();
+        cache.incr_by("session:count", 1, None).unwrap();
+        cache.set_if_absent("session:lock", String::from("1"), None).unwrap();
+        cache.set("user:1", String::from("1"), None).unwrap();
+
+        assert!(cache.ttl("session:1").unwrap().unwrap().is_some_and(|ttl| (59..=60).contains(&ttl)));
+        assert!(cache.ttl("session:2").unwrap().unwrap().is_some_and(|ttl| ttl <= 10));
+        assert!(cache.ttl("session:count").unwrap().unwrap().is_some_and(|ttl| ttl <= 60));
+        assert!(cache.ttl("session:lock").unwrap().unwrap().is_some_and(|ttl| ttl <= 60));
+        // 默认过期时间不影响已有的缓存项和其他命名空间
+        assert_eq!(cache.ttl("session:old"), Ok(Some(None)));
+        assert_eq!(cache.ttl("user:1"), Ok(Some(None)));
+
+        let session = cache.namespaces().unwrap().into_iter().find(|namespace| namespace.name == "session").unwrap();
+        assert_eq!((session.entries, session.default_ttl_secs), (5, Some(60)));
+    }
+
+    #[test]
+    fn scan_pages_through_all_shards_in_key_order() {
+        let cache = test_cache();
+        let mut expected: Vec<String> = (0..250).map(|i| format!("item:{:03}", i)).collect();
+        for key in &expected {
+            cache.set(key, String::from("1"), Some(3600)).unwrap();
+        }
+        // 已过期的缓存项不出现在结果中
+        expire_now(&cache, "item:100");
+        expected.retain(|key| key != "item:100");
+
+        let mut scanned = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = cache.scan(cursor.as_deref(), 40, |_| true).unwrap();
+            assert!(page.len() <= 40);
+            assert!(page.iter().all(|info| info.ttl.is_some_and(|ttl| ttl <= 3600)));
+            let Some(last) = page.last() else { break };
+            cursor = Some(last.key.clone());
+            scanned.extend(page.into_iter().map(|info| info.key));
+        }
+        assert_eq!(scanned, expected);
+
+        // 过滤条件在分页之前应用，每页仍然填满
+        let page = cache.scan(Some("item:050"), 5, |key| key.ends_with('7')).unwrap();
+        let keys: Vec<&str> = page.iter().map(|info| info.key.as_str()).collect();
+        assert_eq!(keys, vec!["item:057", "item:067", "item:077", "item:087", "item:097"]);
+    }
+
+    #[test]
+    fn ttl_expire_and_persist_report_missing_persistent_and_expiring_keys() {
+        let cache = test_cache();
+        assert_eq!(cache.ttl("missing"), Ok(None));
+        assert_eq!(cache.expire("missing", 30), Ok(false));
+        assert_eq!(cache.persist("missing"), Ok(false));
+
+        cache.set("persistent", String::from("1"), None).unwrap();
+        assert_eq!(cache.ttl("persistent"), Ok(Some(None)));
+        assert_eq!(cache.persist("persistent"), Ok(true));
+        assert_eq!(cache.ttl("persistent"), Ok(Some(None)));
+        assert_eq!(cache.expire("persistent", 30), Ok(true));
+        assert!(cache.ttl("persistent").unwrap().unwrap().is_some_and(|ttl| (29..=30).contains(&ttl)));
+
+        cache.set("expiring", String::from("1"), Some(60)).unwrap();
+        assert!(cache.ttl("expiring").unwrap().unwrap().is_some_and(|ttl| (59..=60).contains(&ttl)));
+        assert_eq!(cache.persist("expiring"), Ok(true));
+        assert_eq!(cache.ttl("expiring"), Ok(Some(None)));
+
+        // 已过期的缓存项按不存在处理
+        cache.set("expired", String::from("1"), Some(60)).unwrap();
+        expire_now(&cache, "expired");
+        assert_eq!(cache.ttl("expired"), Ok(None));
+        assert_eq!(cache.expire("expired", 30), Ok(false));
+        assert_eq!(cache.persist("expired"), Ok(false));
+    }
+
+    #[test]
+    fn stats_count_hits_misses_writes_evictions_and_expirations() {
+        let cache = single_shard(CacheConfig { max_entries: 3, ..CacheConfig::default() });
+        cache.set("a", String::from("1"), None).unwrap();
+        cache.set("b", String::from("1"), Some(3600)).unwrap();
+        cache.set("a", String::from("2"), None).unwrap();
+        cache.get("a").unwrap();
+        cache.get("a").unwrap();
+        cache.get("missing").unwrap();
+
+        // 读取时发现的过期缓存项计为一次过期和一次未命中
+        expire_now(&cache, "b");
+        cache.get("b").unwrap();
+        // 写入第4个缓存项时淘汰最久未使用的缓存项
+        for key in ["c", "d", "e"] {
+            cache.set(key, String::from("1"), None).unwrap();
+        }
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+        assert_eq!(stats.hit_rate, 0.5);
+        assert_eq!((stats.inserts, stats.overwrites), (5, 1));
+        assert_eq!((stats.expirations, stats.evictions), (1, 1));
+        assert_eq!((stats.entries, stats.max_entries), (3, 3));
+        assert_eq!(stats.namespaces.get(DEFAULT_NAMESPACE), Some(&3));
+
+        // 重置只清零计数，不影响缓存项
+        cache.reset_stats();
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.inserts, stats.overwrites, stats.expirations, stats.evictions), (0, 0, 0, 0, 0, 0));
+        assert_eq!(stats.hit_rate, 0.0);
+        assert_eq!(stats.entries, 3);
+        assert_eq!(cache.get("e"), Ok(Some(String::from("1"))));
+        assert_eq!(cache.stats().unwrap().hits, 1);
+    }
+
+    #[test]
+    fn stats_purge_expired_entries_before_counting() {
+        let cache = test_cache();
+        for i in 0..10 {
+            cache.set(&format!("user:{}", i), String::from("1"), Some(3600)).unwrap();
+        }
+        expire_now(&cache, "user:3");
+        expire_now(&cache, "user:7");
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.entries, stats.expirations), (8, 2));
+        assert_eq!(stats.namespaces.get("user"), Some(&8));
+        assert_eq!(stats.bytes, 8 * "user:0".len() + 8);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+        manager: Option<u64>,
+    }
+
+    #[test]
+    fn typed_values_round_trip_through_json() {
+        let cache = test_cache();
+        let profile = Profile { id: 1, name: String::from("alice"), tags: vec![String::from("admin")], manager: None };
+        cache.set_typed("profile:1", &profile, Some(60)).unwrap();
+        cache.set_typed("numbers", &[1, 2, 3][..], None).unwrap();
+        cache.set_typed("flag", &true, None).unwrap();
+
+        assert_eq!(cache.get_typed::<Profile>("profile:1"), Ok(Some(profile)));
+        assert_eq!(cache.get_typed::<Vec<i32>>("numbers"), Ok(Some(vec![1, 2, 3])));
+        assert_eq!(cache.get_typed::<bool>("flag"), Ok(Some(true)));
+        assert_eq!(cache.get_typed::<Profile>("profile:2"), Ok(None));
+        // 以JSON文本保存，可以通过 get 读取原始值
+        assert_eq!(cache.get("numbers"), Ok(Some(String::from("[1,2,3]"))));
+        assert!(cache.ttl("profile:1").unwrap().unwrap().is_some());
+    }
+
+    #[test]
+    fn reading_a_typed_value_as_another_type_fails() {
+        let cache = test_cache();
+        cache.set_typed("count", &42, None).unwrap();
+        cache.set("raw", String::from("not json"), None).unwrap();
+
+        let err = cache.get_typed::<Profile>("count").unwrap_err();
+        assert!(err.contains("反序列化缓存项 'count' 失败"), "{}", err);
+        assert!(cache.get_typed::<String>("count").is_err());
+        assert!(cache.get_typed::<u64>("raw").is_err());
+        // 读取失败不删除缓存项
+        assert_eq!(cache.get_typed::<u64>("count"), Ok(Some(42)));
+    }
+
+    fn stored(cache: &Cache, key: &str) -> bool {
+        cache.shard(key).unwrap().items.contains_key(key)
+    }
+
+    #[test]
+    fn expired_entries_are_removed_lazily_on_read() {
+        let cache = test_cache();
+        cache.set("session:1", String::from("1"), Some(3600)).unwrap();
+        cache.set("session:2", String::from("1"), Some(3600)).unwrap();
+        expire_now(&cache, "session:1");
+        expire_now(&cache, "session:2");
+
+        // 过期后读取前仍在分片中，读取时删除
+        assert!(stored(&cache, "session:1"));
+        assert_eq!(cache.get("session:1"), Ok(None));
+        assert!(!stored(&cache, "session:1"));
+        assert!(stored(&cache, "session:2"));
+
+        // 条件写入同样把已过期的缓存项当作不存在
+        assert_eq!(cache.set_if_absent("session:2", String::from("2"), None), Ok(true));
+        assert_eq!(cache.get("session:2"), Ok(Some(String::from("2"))));
+        assert_eq!(cache.stats().unwrap().expirations, 2);
+    }
+
+    #[test]
+    fn purge_expired_removes_due_entries_through_the_expiry_index() {
+        let cache = single_shard(CacheConfig::unbounded());
+        // 超过单批清理数量，验证分批清理能清理完
+        let due = SWEEP_BATCH_SIZE * 2 + 500;
+        for i in 0..due {
+            let key = format!("due:{}", i);
+            cache.set(&key, String::from("1"), Some(3600)).unwrap();
+            expire_now(&cache, &key);
+        }
+        for i in 0..100 {
+            cache.set(&format!("later:{}", i), String::from("1"), Some(3600)).unwrap();
+            cache.set(&format!("forever:{}", i), String::from("1"), None).unwrap();
+        }
+
+        assert_eq!(cache.purge_expired(), Ok(due));
+        let inner = cache.shard("later:0").unwrap();
+        assert_eq!(inner.items.len(), 200);
+        assert_eq!(inner.expiries.len(), 100);
+        assert!(inner.expiries.values().all(|key| key.starts_with("later:")));
+        drop(inner);
+        assert_eq!(cache.purge_expired(), Ok(0));
+        assert_eq!(cache.stats().unwrap().expirations as usize, due);
+    }
+
+    #[tokio::test]
+    async fn sweeper_removes_expired_entries_without_reads() {
+        let cache = test_cache();
+        cache.set("session:1", String::from("1"), Some(3600)).unwrap();
+        cache.set("session:2", String::from("1"), Some(3600)).unwrap();
+        expire_now(&cache, "session:1");
+
+        let sweeper = spawn_sweeper(cache.clone(), Duration::from_millis(10));
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while stored(&cache, "session:1") {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }).await.unwrap();
+        sweeper.abort();
+        assert!(stored(&cache, "session:2"));
+    }
+
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 1000;
+
+    // 在多个线程上同时执行 f(线程序号)，等所有线程结束后返回各自的结果
+    fn run_threads<T: Send + 'static>(cache: &Cache, f: impl Fn(&Cache, usize) -> T + Send + Sync + 'static) -> Vec<T> {
+        let f = Arc::new(f);
+        let barrier = Arc::new(std::sync::Barrier::new(THREADS));
+        let handles: Vec<_> = (0..THREADS)
+            .map(|i| {
+                let (cache, f, barrier) = (cache.clone(), f.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    f(&cache, i)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        let cache = test_cache();
+        run_threads(&cache, |cache, _| {
+            for _ in 0..ROUNDS {
+                cache.incr_by("counter", 1, None).unwrap();
+            }
+        });
+        assert_eq!(cache.get("counter"), Ok(Some((THREADS * ROUNDS).to_string())));
+    }
+
+    #[test]
+    fn only_one_concurrent_set_if_absent_wins() {
+        let cache = test_cache();
+        for round in 0..ROUNDS / 10 {
+            let key = format!("lock:{}", round);
+            let winners = run_threads(&cache, move |cache, i| cache.set_if_absent(&key, i.to_string(), None).unwrap());
+            assert_eq!(winners.iter().filter(|&&won| won).count(), 1);
+        }
+    }
+
+    #[test]
+    fn concurrent_compare_and_swap_loses_no_updates() {
+        let cache = test_cache();
+        cache.set("version", String::from("0"), None).unwrap();
+        run_threads(&cache, |cache, _| {
+            for _ in 0..ROUNDS {
+                loop {
+                    let current = cache.get("version").unwrap().unwrap();
+                    let next = (current.parse::<usize>().unwrap() + 1).to_string();
+                    if cache.compare_and_swap("version", &current, next, None).unwrap() {
+                        break;
+                    }
+                }
+            }
+        });
+        assert_eq!(cache.get("version"), Ok(Some((THREADS * ROUNDS).to_string())));
+    }
+}
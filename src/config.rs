@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use serde::Deserialize;
+use crate::cache::CacheConfig;
+use crate::middleware::JsonLoggerConfig;
+use crate::middleware::public_routes::PublicRoutes;
+
+// 配置文件目录，可通过 APP_CONFIG_DIR 覆盖
+const DEFAULT_CONFIG_DIR: &str = "config";
+// 环境变量覆盖前缀，例如 APP_SERVER__PORT=9090 覆盖 server.port
+const ENV_PREFIX: &str = "APP";
+// 开发环境默认的JWT密钥，生产环境禁止使用
+const DEV_JWT_SECRET: &str = "your-default-secret-key-1234567890";
+
+// 全局配置实例，启动时初始化一次
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+// 运行环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Dev,
+    Test,
+    Prod,
+}
+
+impl Profile {
+    // 从字符串解析运行环境
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => Some(Profile::Dev),
+            "test" => Some(Profile::Test),
+            "prod" | "production" => Some(Profile::Prod),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Dev => "dev",
+            Profile::Test => "test",
+            Profile::Prod => "prod",
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// HTTP服务配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub workers: Option<usize>, // 不设置时使用actix默认值（CPU核数）
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: String::from("127.0.0.1"),
+            port: 8080,
+            workers: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    // 监听地址，例如 127.0.0.1:8080
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+// MySQL配置，同时供 r2d2 连接池和 rbatis 使用
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub name: String,
+    pub max_connections: u32,
+    pub min_idle: Option<u32>,
+    pub connection_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            host: String::from("127.0.0.1"),
+            port: 3306,
+            user: String::new(),
+            password: String::new(),
+            name: String::new(),
+            max_connections: 10,
+            min_idle: Some(0),
+            connection_timeout_secs: 5,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    // 生成 rbatis 使用的连接串
+    pub fn url(&self) -> String {
+        format!(
+            "mysql://{}:{}@{}:{}/{}",
+            percent_encode(&self.user),
+            percent_encode(&self.password),
+            self.host,
+            self.port,
+            self.name
+        )
+    }
+}
+
+// Redis配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedisConfig {
+    pub url: String,
+    pub max_connections: Option<usize>,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: String::from("redis://localhost:6379/1"),
+            max_connections: None,
+        }
+    }
+}
+
+// 免认证路由规则，path（精确）、prefix（前缀）、glob（通配符）三者必须且只能设置一个
+// methods 为空时匹配所有请求方法
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct PublicRouteConfig {
+    pub path: Option<String>,
+    pub prefix: Option<String>,
+    pub glob: Option<String>,
+    pub methods: Vec<String>,
+}
+
+impl PublicRouteConfig {
+    fn new(path: Option<&str>, prefix: Option<&str>, glob: Option<&str>, methods: &[&str]) -> Self {
+        Self {
+            path: path.map(String::from),
+            prefix: prefix.map(String::from),
+            glob: glob.map(String::from),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+}
+
+// 默认的免认证路由
+fn default_public_routes() -> Vec<PublicRouteConfig> {
+    vec![
+        PublicRouteConfig::new(Some("/auth/login"), None, None, &["POST"]),
+        PublicRouteConfig::new(Some("/auth/register"), None, None, &["POST"]),
+        PublicRouteConfig::new(Some("/auth/refresh"), None, None, &["POST"]),
+        PublicRouteConfig::new(Some("/rbatis/health"), None, None, &["GET"]),
+        PublicRouteConfig::new(Some("/api/health"), None, None, &["GET"]),
+        PublicRouteConfig::new(None, None, Some("/api/health/*"), &["GET"]),
+        PublicRouteConfig::new(Some("/favicon.ico"), None, None, &["GET"]),
+        PublicRouteConfig::new(Some("/.well-known/jwks.json"), None, None, &["GET"]),
+    ]
+}
+
+// 非对称签名密钥（PEM文件），退役的密钥可以只配置公钥，用于验证轮换前签发的令牌
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: String, // RS256, ES256 或 EdDSA
+    pub private_key_path: Option<String>,
+    pub public_key_path: String,
+}
+
+// JWT配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JwtConfig {
+    pub secret: String,                 // 未配置 keys 时使用 HS256 共享密钥
+    pub keys: Vec<JwtKeyConfig>,        // 非对称密钥，配置后不再使用 secret
+    pub signing_kid: Option<String>,    // 用于签发令牌的密钥
+    pub access_token_ttl_secs: u64,  // 访问令牌有效期
+    pub refresh_token_ttl_secs: u64, // 刷新令牌有效期，每次轮换重新计算
+    pub public_routes: Vec<PublicRouteConfig>, // 无需JWT即可访问的路由，配置后整体替换默认列表
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            secret: String::from(DEV_JWT_SECRET),
+            keys: Vec::new(),
+            signing_kid: None,
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_secs: 30 * 24 * 60 * 60,
+            public_routes: default_public_routes(),
+        }
+    }
+}
+
+// 密码哈希配置（argon2id），修改后旧哈希会在用户下次登录时自动升级
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+// 登录失败限制配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginThrottleConfig {
+    pub enabled: bool,
+    pub max_failures_per_phone: u32, // 同一手机号在窗口内的最大失败次数，达到后锁定
+    pub max_failures_per_ip: u32,    // 同一IP在窗口内的最大失败次数，达到后锁定
+    pub failure_window_secs: u64,    // 失败计数窗口，从第一次失败开始计算
+    pub lockout_secs: u64,           // 锁定时长
+    pub delay_after_failures: u32,   // 超过该失败次数后开始延迟响应
+    pub base_delay_ms: u64,          // 首次延迟，之后每次失败翻倍
+    pub max_delay_ms: u64,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_failures_per_phone: 5,
+            max_failures_per_ip: 20,
+            failure_window_secs: 15 * 60,
+            lockout_secs: 15 * 60,
+            delay_after_failures: 2,
+            base_delay_ms: 500,
+            max_delay_ms: 5000,
+        }
+    }
+}
+
+// 两级缓存配置：进程内缓存在前，Redis在后
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LayeredCacheConfig {
+    pub key_prefix: String,  // Redis中的键前缀
+    pub local_ttl_secs: u64, // 进程内缓存的最长保存时间，限制多实例之间不一致的时长
+}
+
+impl Default for LayeredCacheConfig {
+    fn default() -> Self {
+        Self {
+            key_prefix: String::from("cache:"),
+            local_ttl_secs: 30,
+        }
+    }
+}
+
+// 限流存储后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    #[default]
+    Redis,  // 多实例共享计数，Redis不可用时退回进程内计数
+    Memory, // 仅在当前进程内计数
+}
+
+// 单个scope的限流规则：每个调用方在 window_secs 秒内最多 requests 次请求
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitRule {
+    pub requests: u32,
+    pub window_secs: u64,
+}
+
+// 限流配置，scopes 的键为路由scope名称（api, rbatis, cache, redis），未配置的scope不限流
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub backend: RateLimitBackend,
+    pub scopes: HashMap<String, RateLimitRule>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let scopes = [("api", 120), ("rbatis", 60), ("cache", 300), ("redis", 300)]
+            .into_iter()
+            .map(|(scope, requests)| (scope.to_string(), RateLimitRule { requests, window_secs: 60 }))
+            .collect();
+        Self {
+            enabled: true,
+            backend: RateLimitBackend::Redis,
+            scopes,
+        }
+    }
+}
+
+// 健康检查配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub timeout_ms: u64,       // 单个依赖探测的超时时间
+    pub redis_required: bool,  // Redis不可用时是否判定为未就绪
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 1000,
+            redis_required: false,
+        }
+    }
+}
+
+// 应用配置
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    pub profile: Profile,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub redis: RedisConfig,
+    pub jwt: JwtConfig,
+    pub logger: JsonLoggerConfig,
+    pub health: HealthConfig,
+    pub password: PasswordConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub layered_cache: LayeredCacheConfig,
+}
+
+// 配置加载错误
+#[derive(Debug)]
+pub enum ConfigError {
+    UnknownProfile(String),
+    Load(::config::ConfigError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnknownProfile(value) => {
+                write!(f, "未知的运行环境 '{}'，可选值: dev, test, prod", value)
+            },
+            ConfigError::Load(err) => write!(f, "读取配置失败: {}", err),
+            ConfigError::Invalid(errors) => {
+                writeln!(f, "配置校验失败:")?;
+                for err in errors {
+                    writeln!(f, "  - {}", err)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<::config::ConfigError> for ConfigError {
+    fn from(err: ::config::ConfigError) -> Self {
+        ConfigError::Load(err)
+    }
+}
+
+impl AppConfig {
+    // 按以下顺序加载配置，后者覆盖前者：
+    // 1. config/default.{toml,yaml}
+    // 2. config/{profile}.{toml,yaml}
+    // 3. config/local.{toml,yaml}，不纳入版本控制，用于保存本机的数据库账号等敏感配置
+    // 4. 以 APP_ 为前缀的环境变量（层级用双下划线分隔）
+    // 运行环境由 APP_PROFILE 指定，默认为 dev
+    pub fn load() -> Result<Self, ConfigError> {
+        let profile_name = std::env::var("APP_PROFILE").unwrap_or_else(|_| String::from("dev"));
+        let profile = Profile::parse(&profile_name)
+            .ok_or(ConfigError::UnknownProfile(profile_name))?;
+        let config_dir = std::env::var("APP_CONFIG_DIR").unwrap_or_else(|_| String::from(DEFAULT_CONFIG_DIR));
+
+        let settings = ::config::Config::builder()
+            .add_source(::config::File::with_name(&format!("{}/default", config_dir)).required(false))
+            .add_source(::config::File::with_name(&format!("{}/{}", config_dir, profile)).required(false))
+            .add_source(::config::File::with_name(&format!("{}/local", config_dir)).required(false))
+            .add_source(
+                ::config::Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .set_override("profile", profile.as_str())?
+            .build()?;
+
+        let app_config: AppConfig = settings.try_deserialize()?;
+        app_config.validate()?;
+        Ok(app_config)
+    }
+
+    // 校验配置，一次性收集所有错误
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.server.host.trim().is_empty() {
+            errors.push(String::from("server.host 不能为空"));
+        }
+        if self.server.port == 0 {
+            errors.push(String::from("server.port 必须大于0"));
+        }
+        if self.server.workers == Some(0) {
+            errors.push(String::from("server.workers 必须大于0"));
+        }
+
+        if self.database.host.trim().is_empty() {
+            errors.push(String::from("database.host 不能为空"));
+        }
+        if self.database.user.trim().is_empty() {
+            errors.push(String::from("database.user 不能为空"));
+        }
+        if self.database.name.trim().is_empty() {
+            errors.push(String::from("database.name 不能为空"));
+        }
+        if self.database.max_connections == 0 {
+            errors.push(String::from("database.max_connections 必须大于0"));
+        }
+        if let Some(min_idle) = self.database.min_idle
+            && min_idle > self.database.max_connections {
+            errors.push(String::from("database.min_idle 不能大于 database.max_connections"));
+        }
+
+        if !self.redis.url.starts_with("redis://") && !self.redis.url.starts_with("rediss://") {
+            errors.push(format!("redis.url 格式无效: '{}'", self.redis.url));
+        }
+        if self.redis.max_connections == Some(0) {
+            errors.push(String::from("redis.max_connections 必须大于0"));
+        }
+
+        if self.jwt.keys.is_empty() && self.jwt.secret.is_empty() {
+            errors.push(String::from("jwt.secret 不能为空"));
+        }
+        for (index, key) in self.jwt.keys.iter().enumerate() {
+            if key.kid.trim().is_empty() {
+                errors.push(format!("jwt.keys[{}].kid 不能为空", index));
+            } else if self.jwt.keys[..index].iter().any(|other| other.kid == key.kid) {
+                errors.push(format!("jwt.keys[{}].kid '{}' 重复", index, key.kid));
+            }
+            if !matches!(key.algorithm.as_str(), "RS256" | "ES256" | "EdDSA") {
+                errors.push(format!("jwt.keys[{}].algorithm 无效: '{}'，可选值: RS256, ES256, EdDSA", index, key.algorithm));
+            }
+            if key.public_key_path.trim().is_empty() {
+                errors.push(format!("jwt.keys[{}].public_key_path 不能为空", index));
+            }
+        }
+        if !self.jwt.keys.is_empty() {
+            match &self.jwt.signing_kid {
+                None => errors.push(String::from("配置了 jwt.keys 时必须设置 jwt.signing_kid")),
+                Some(kid) => {
+                    if !self.jwt.keys.iter().any(|key| &key.kid == kid && key.private_key_path.is_some()) {
+                        errors.push(format!("jwt.signing_kid '{}' 必须对应一个配置了 private_key_path 的密钥", kid));
+                    }
+                },
+            }
+        }
+        if self.jwt.access_token_ttl_secs == 0 {
+            errors.push(String::from("jwt.access_token_ttl_secs 必须大于0"));
+        }
+        if self.jwt.refresh_token_ttl_secs <= self.jwt.access_token_ttl_secs {
+            errors.push(String::from("jwt.refresh_token_ttl_secs 必须大于 jwt.access_token_ttl_secs"));
+        }
+        for (index, route) in self.jwt.public_routes.iter().enumerate() {
+            if let Err(e) = PublicRoutes::from_config(std::slice::from_ref(route)) {
+                errors.push(format!("jwt.public_routes[{}]: {}", index, e));
+            }
+        }
+        if self.profile == Profile::Prod && self.jwt.keys.is_empty() {
+            if self.jwt.secret == DEV_JWT_SECRET {
+                errors.push(String::from("生产环境必须通过 APP_JWT__SECRET 设置 jwt.secret"));
+            } else if self.jwt.secret.len() < 32 {
+                errors.push(String::from("生产环境的 jwt.secret 长度不能少于32个字符"));
+            }
+        }
+
+        if self.logger.log_dir.trim().is_empty() {
+            errors.push(String::from("logger.log_dir 不能为空"));
+        }
+        if self.logger.max_file_size_mb == 0 {
+            errors.push(String::from("logger.max_file_size_mb 必须大于0"));
+        }
+
+        if self.password.iterations == 0 {
+            errors.push(String::from("password.iterations 必须大于0"));
+        }
+        if self.password.parallelism == 0 {
+            errors.push(String::from("password.parallelism 必须大于0"));
+        }
+        if self.password.memory_kib < 8 * self.password.parallelism {
+            errors.push(String::from("password.memory_kib 不能小于 8 * password.parallelism"));
+        }
+
+        if self.login_throttle.enabled {
+            if self.login_throttle.max_failures_per_phone == 0 {
+                errors.push(String::from("login_throttle.max_failures_per_phone 必须大于0"));
+            }
+            if self.login_throttle.max_failures_per_ip == 0 {
+                errors.push(String::from("login_throttle.max_failures_per_ip 必须大于0"));
+            }
+            if self.login_throttle.failure_window_secs == 0 {
+                errors.push(String::from("login_throttle.failure_window_secs 必须大于0"));
+            }
+            if self.login_throttle.lockout_secs == 0 {
+                errors.push(String::from("login_throttle.lockout_secs 必须大于0"));
+            }
+            if self.login_throttle.base_delay_ms > self.login_throttle.max_delay_ms {
+                errors.push(String::from("login_throttle.base_delay_ms 不能大于 login_throttle.max_delay_ms"));
+            }
+        }
+
+        for (scope, rule) in &self.rate_limit.scopes {
+            if rule.requests == 0 {
+                errors.push(format!("rate_limit.scopes.{}.requests 必须大于0", scope));
+            }
+            if rule.window_secs == 0 {
+                errors.push(format!("rate_limit.scopes.{}.window_secs 必须大于0", scope));
+            }
+        }
+
+        if self.cache.max_entries == 0 {
+            errors.push(String::from("cache.max_entries 必须大于0"));
+        }
+        if self.cache.max_bytes == 0 {
+            errors.push(String::from("cache.max_bytes 必须大于0"));
+        }
+        if self.cache.shards == 0 {
+            errors.push(String::from("cache.shards 必须大于0"));
+        } else if self.cache.shards > self.cache.max_entries {
+            errors.push(String::from("cache.shards 不能大于 cache.max_entries"));
+        }
+        if self.cache.sweep_interval_secs == 0 {
+            errors.push(String::from("cache.sweep_interval_secs 必须大于0"));
+        }
+        for (name, namespace) in &self.cache.namespaces {
+            if name.is_empty() || name.contains(':') {
+                errors.push(format!("cache.namespaces 中的名称 '{}' 不能为空或包含 ':'", name));
+            }
+            if namespace.default_ttl_secs == Some(0) {
+                errors.push(format!("cache.namespaces.{}.default_ttl_secs 必须大于0", name));
+            }
+            match namespace.max_entries {
+                Some(0) => errors.push(format!("cache.namespaces.{}.max_entries 必须大于0", name)),
+                Some(max_entries) if max_entries < self.cache.shards => {
+                    errors.push(format!("cache.namespaces.{}.max_entries 不能小于 cache.shards", name));
+                },
+                _ => {},
+            }
+        }
+        if self.cache.snapshot_path.as_deref().is_some_and(|path| path.trim().is_empty()) {
+            errors.push(String::from("cache.snapshot_path 不能为空字符串，不需要快照时请删除该配置"));
+        }
+        if self.cache.snapshot_path.is_some() && self.cache.snapshot_interval_secs == 0 {
+            errors.push(String::from("cache.snapshot_interval_secs 必须大于0"));
+        }
+
+        if self.layered_cache.local_ttl_secs == 0 {
+            errors.push(String::from("layered_cache.local_ttl_secs 必须大于0"));
+        }
+
+        if self.health.timeout_ms == 0 {
+            errors.push(String::from("health.timeout_ms 必须大于0"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+// 初始化全局配置，只能调用一次
+pub fn init(app_config: AppConfig) -> &'static AppConfig {
+    APP_CONFIG.get_or_init(|| app_config)
+}
+
+// 获取全局配置，必须先调用 init
+pub fn get() -> &'static AppConfig {
+    APP_CONFIG.get().expect("配置尚未初始化，请先调用 config::init")
+}
+
+// 对连接串中的用户名和密码做百分号编码
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
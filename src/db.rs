@@ -4,8 +4,10 @@ use mysql::OptsBuilder;
 use r2d2::Pool;
 use r2d2_mysql::MySqlConnectionManager;
 use std::sync::Arc;
+use std::time::Duration;
 use actix_web::{web, error, Error};
 use serde::{Deserialize, Serialize};
+use crate::config::DatabaseConfig;
 
 // 定义响应数据结构
 #[derive(Serialize)]
@@ -76,10 +78,54 @@ pub struct UpdateUserRequest {
     pub is_ban: Option<u8>,
 }
 
-// 定义请求数据结构
-#[derive(Deserialize)]
-pub struct GreetRequest {
-    pub name: Option<String>,
+// 新注册用户默认授予的角色
+pub const DEFAULT_ROLE: &str = "user";
+
+// 用户的角色与权限，签发令牌时写入JWT
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserGrants {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl UserGrants {
+    // 是否拥有指定权限，'*' 表示全部权限
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == "*" || p == permission)
+    }
+}
+
+// 用户是否存在
+pub fn user_exists(conn: &mut Conn, user_id: u64) -> std::result::Result<bool, mysql::Error> {
+    let row: Option<u64> = conn.exec_first("SELECT id FROM users WHERE id = ? LIMIT 1", (user_id,))?;
+    Ok(row.is_some())
+}
+
+// 从数据库加载用户的角色与权限
+pub fn load_user_grants(conn: &mut Conn, user_id: u64) -> std::result::Result<UserGrants, mysql::Error> {
+    let rows: Vec<(String, Option<String>)> = conn.exec(
+        "SELECT ur.role, rp.permission FROM user_roles ur \
+         LEFT JOIN role_permissions rp ON rp.role = ur.role \
+         WHERE ur.user_id = ?",
+        (user_id,)
+    )?;
+    
+    let mut grants = UserGrants::default();
+    for (role, permission) in rows {
+        if !grants.roles.contains(&role) {
+            grants.roles.push(role);
+        }
+        if let Some(permission) = permission
+            && !grants.permissions.contains(&permission) {
+            grants.permissions.push(permission);
+        }
+    }
+    Ok(grants)
+}
+
+// 为用户授予角色，已存在时忽略
+pub fn grant_role(conn: &mut Conn, user_id: u64, role: &str) -> std::result::Result<(), mysql::Error> {
+    conn.exec_drop("INSERT IGNORE INTO user_roles (user_id, role) VALUES (?, ?)", (user_id, role))
 }
 
 // 数据库连接池类型别名
@@ -98,17 +144,20 @@ pub async fn get_connection_or_return_error(
 }
 
 // 初始化数据库连接池
-pub fn init_db_pool() -> Result<DbPool, Box<dyn std::error::Error>> {
+pub fn init_db_pool(config: &DatabaseConfig) -> Result<DbPool, Box<dyn std::error::Error>> {
     let opts = OptsBuilder::new()
-        .ip_or_hostname(Some("172.17.0.185"))
-        .tcp_port(3306)
-        .user(Some("admin"))
-        .pass(Some("b7371d927aec647d"))
-        .db_name(Some("grave"));
+        .ip_or_hostname(Some(config.host.as_str()))
+        .tcp_port(config.port)
+        .user(Some(config.user.as_str()))
+        .pass(Some(config.password.as_str()))
+        .db_name(Some(config.name.as_str()));
     let manager = MySqlConnectionManager::new(opts);
     let pool = Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(config.min_idle)
+        .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
         .build(manager)
         .map_err(|e| format!("Failed to create pool: {}", e))?;
     
     Ok(Arc::new(pool))
-}
\ No newline at end of file
+}
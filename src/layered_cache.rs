@@ -0,0 +1,213 @@
+use deadpool_redis::Connection;
+use log::{error, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::cache::Cache;
+use crate::config::LayeredCacheConfig;
+use crate::redis_pool::{self, RedisPool};
+
+// 两级缓存：先查进程内缓存（L1），未命中时查Redis（L2）并回填L1，写入和删除同时作用于两级
+// L1中的缓存项最多保存 local_ttl_secs 秒，其他实例修改或删除后最迟在这段时间后可见
+// Redis未初始化或不可用时只使用L1，此时写入L1的缓存项保留完整的过期时间
+pub struct LayeredCache {
+    local: Cache,
+    redis: RedisPool,
+    config: LayeredCacheConfig,
+}
+
+impl LayeredCache {
+    pub fn new(local: Cache, redis: RedisPool, config: LayeredCacheConfig) -> Self {
+        Self { local, redis, config }
+    }
+
+    // 读取缓存项，Redis出错时按未命中处理
+    pub async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        if let Some(value) = self.local.get(key)? {
+            return Ok(Some(value));
+        }
+
+        let Some(mut conn) = self.redis_connection().await else {
+            return Ok(None);
+        };
+        let redis_key = self.redis_key(key);
+        let value = match redis_pool::get(&mut conn, &redis_key).await {
+            Ok(Some(value)) => value,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                error!("从Redis读取缓存项失败: {}, key={}", e, key);
+                return Ok(None);
+            },
+        };
+
+        // 回填L1，保存时间不超过Redis中的剩余过期时间
+        let remaining = match redis_pool::ttl(&mut conn, &redis_key).await {
+            Ok(ttl) if ttl > 0 => Some(ttl as u64),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("读取Redis缓存项过期时间失败: {}, key={}", e, key);
+                None
+            },
+        };
+        let local_ttl = remaining.map_or(self.config.local_ttl_secs, |ttl| ttl.min(self.config.local_ttl_secs));
+        if let Err(e) = self.local.set(key, value.clone(), Some(local_ttl)) {
+            warn!("回填进程内缓存失败: {}, key={}", e, key);
+        }
+        Ok(Some(value))
+    }
+
+    // 写入缓存项，ttl 为空表示不过期
+    pub async fn set(&self, key: &str, value: String, ttl: Option<u64>) -> Result<(), String> {
+        let written = match self.redis_connection().await {
+            Some(mut conn) => {
+                let redis_key = self.redis_key(key);
+                let result = match ttl {
+                    Some(ttl) => redis_pool::set_with_expiry(&mut conn, &redis_key, &value, ttl).await,
+                    None => redis_pool::set(&mut conn, &redis_key, &value).await,
+                };
+                match result {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error!("写入Redis缓存项失败，只写入进程内缓存: {}, key={}", e, key);
+                        false
+                    },
+                }
+            },
+            None => false,
+        };
+
+        let local_ttl = if written {
+            Some(ttl.map_or(self.config.local_ttl_secs, |ttl| ttl.min(self.config.local_ttl_secs)))
+        } else {
+            ttl
+        };
+        self.local.set(key, value, local_ttl).map_err(String::from)
+    }
+
+    // 删除两级缓存中的缓存项
+    pub async fn remove(&self, key: &str) -> Result<(), String> {
+        self.local.remove(key)?;
+        if let Some(mut conn) = self.redis_connection().await
+            && let Err(e) = redis_pool::del(&mut conn, &self.redis_key(key)).await {
+            error!("删除Redis缓存项失败: {}, key={}", e, key);
+        }
+        Ok(())
+    }
+
+    // 以JSON序列化后写入两级缓存
+    pub async fn set_typed<T: Serialize + ?Sized>(&self, key: &str, value: &T, ttl: Option<u64>) -> Result<(), String> {
+        let raw = serde_json::to_string(value).map_err(|e| format!("序列化缓存项 '{}' 失败: {}", key, e))?;
+        self.set(key, raw, ttl).await
+    }
+
+    // 读取并反序列化缓存项
+    pub async fn get_typed<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key).await? {
+            Some(raw) => serde_json::from_str(&raw)
+                .map(Some)
+                .map_err(|e| format!("反序列化缓存项 '{}' 失败: {}", key, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}{}", self.config.key_prefix, key)
+    }
+
+    // 获取Redis连接，连接池未初始化或连接失败时返回None并只使用进程内缓存
+    async fn redis_connection(&self) -> Option<Connection> {
+        redis_pool::get_connection_or_fallback(&self.redis, "两级缓存").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use crate::config::RedisConfig;
+    use crate::routes::test_support;
+    use crate::utils::time::unix_now_millis;
+    use super::*;
+
+    fn layered(redis: RedisPool) -> (LayeredCache, Cache) {
+        let local: Cache = Arc::new(SimpleCache::new(CacheConfig::default()));
+        let config = LayeredCacheConfig { key_prefix: String::from("test:layered:"), local_ttl_secs: 30 };
+        (LayeredCache::new(local.clone(), redis, config), local)
+    }
+
+    // 未初始化连接池和连接失败两种情况都只使用L1，且L1保留完整的过期时间
+    #[actix_web::test]
+    async fn unavailable_redis_falls_back_to_l1_with_full_ttl() {
+        let unreachable = redis_pool::init_redis_pool(&RedisConfig {
+            url: String::from("redis://127.0.0.1:1/0"),
+            ..RedisConfig::default()
+        }).unwrap();
+        for redis in [Arc::new(None), unreachable] {
+            let (cache, local) = layered(redis);
+            cache.set("user:1", String::from("alice"), Some(3600)).await.unwrap();
+            cache.set("user:2", String::from("bob"), None).await.unwrap();
+
+            assert!(local.ttl("user:1").unwrap().unwrap().unwrap() > 30);
+            assert_eq!(local.ttl("user:2").unwrap(), Some(None));
+            assert_eq!(cache.get("user:1").await.unwrap().as_deref(), Some("alice"));
+
+            cache.remove("user:1").await.unwrap();
+            assert_eq!(cache.get("user:1").await.unwrap(), None);
+            assert_eq!(cache.get("missing").await.unwrap(), None);
+        }
+    }
+
+    // 以下测试需要测试Redis，见 test_support
+
+    fn unique_key(name: &str) -> String {
+        format!("{}:{}", name, unix_now_millis())
+    }
+
+    async fn redis_value(redis: &RedisPool, key: &str) -> Option<String> {
+        let mut conn = redis_pool::get_connection_or_fallback(redis, "测试").await.unwrap();
+        redis_pool::get(&mut conn, &format!("test:layered:{}", key)).await.unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试Redis"]
+    async fn l1_ttl_is_capped_at_local_ttl_secs() {
+        let (cache, local) = layered(test_support::redis_pool());
+        let (with_ttl, without_ttl) = (unique_key("ttl"), unique_key("no_ttl"));
+
+        cache.set(&with_ttl, String::from("1"), Some(3600)).await.unwrap();
+        cache.set(&without_ttl, String::from("2"), None).await.unwrap();
+        assert!(local.ttl(&with_ttl).unwrap().unwrap().unwrap() <= 30);
+        assert!(local.ttl(&without_ttl).unwrap().unwrap().unwrap() <= 30);
+
+        // L1未命中时从Redis回填，保存时间同样不超过上限，也不超过Redis中的剩余时间
+        local.remove(&with_ttl).unwrap();
+        assert_eq!(cache.get(&with_ttl).await.unwrap().as_deref(), Some("1"));
+        assert!(local.ttl(&with_ttl).unwrap().unwrap().unwrap() <= 30);
+
+        let short = unique_key("short");
+        cache.set(&short, String::from("3"), Some(5)).await.unwrap();
+        local.remove(&short).unwrap();
+        assert_eq!(cache.get(&short).await.unwrap().as_deref(), Some("3"));
+        assert!(local.ttl(&short).unwrap().unwrap().unwrap() <= 5);
+
+        for key in [with_ttl, without_ttl, short] {
+            cache.remove(&key).await.unwrap();
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试Redis"]
+    async fn remove_invalidates_both_layers() {
+        let redis = test_support::redis_pool();
+        let (cache, local) = layered(redis.clone());
+        let key = unique_key("remove");
+
+        cache.set(&key, String::from("value"), Some(60)).await.unwrap();
+        assert_eq!(local.get(&key).unwrap().as_deref(), Some("value"));
+        assert_eq!(redis_value(&redis, &key).await.as_deref(), Some("value"));
+
+        cache.remove(&key).await.unwrap();
+        assert_eq!(local.get(&key).unwrap(), None);
+        assert_eq!(redis_value(&redis, &key).await, None);
+        assert_eq!(cache.get(&key).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,2 @@
+// 库目标，导出可被基准测试等外部目标直接使用的模块
+pub mod cache;
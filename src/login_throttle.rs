@@ -0,0 +1,313 @@
+use std::time::Duration;
+use deadpool_redis::Connection;
+use log::{error, warn};
+use crate::cache::Cache;
+use crate::config::LoginThrottleConfig;
+use crate::redis_pool::{self, RedisPool};
+use crate::utils::time::unix_now;
+
+// 存储键前缀
+const FAILURES_PREFIX: &str = "login_failures:";
+const LOCKED_PREFIX: &str = "login_locked:";
+
+// 登录尝试的检查结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoginCheck {
+    Allowed(LoginAttempt),
+    Locked { retry_after: u64 }, // 距离解锁的秒数
+}
+
+// 已预留的登录尝试，记录预留后手机号和IP的计数，校验密码后交给 record_failure 或 record_success
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LoginAttempt {
+    phone_attempts: u64,
+    ip_attempts: u64,
+}
+
+// 登录失败次数限制：按手机号和客户端IP分别计数
+// 校验密码之前先原子地计入本次尝试，并发的猜测不会在失败被记录之前全部通过检查
+// 登录成功后撤销本次计数；超过阈值后临时锁定，锁定前的失败按次数逐步增加响应延迟
+// 优先使用Redis，Redis未配置或获取连接失败时，该次操作退回进程内缓存
+// 进程内缓存必须不淘汰缓存项（见 CacheConfig::unbounded），否则轮换大量手机号就能把自己的计数和锁定挤出缓存
+// Redis故障期间每个实例各自计数：限制仍然生效，但上限按实例计算，Redis中已有的计数和锁定暂时不可见
+pub struct LoginThrottle {
+    redis: RedisPool,
+    cache: Cache,
+    config: LoginThrottleConfig,
+}
+
+impl LoginThrottle {
+    pub fn new(redis: RedisPool, cache: Cache, config: LoginThrottleConfig) -> Self {
+        Self {
+            redis,
+            cache,
+            config,
+        }
+    }
+
+    // 检查手机号或IP是否处于锁定状态，未锁定时计入本次尝试
+    // 计入后超过上限的尝试直接拒绝并锁定，不再校验密码
+    // 存储出错时放行，避免限流组件故障导致所有用户无法登录
+    pub async fn begin_attempt(&self, phone: &str, ip: &str) -> LoginCheck {
+        if !self.config.enabled {
+            return LoginCheck::Allowed(LoginAttempt::default());
+        }
+
+        let retry_after = self.lock_remaining(phone, ip).await;
+        if retry_after > 0 {
+            return LoginCheck::Locked { retry_after };
+        }
+
+        let mut attempt = LoginAttempt::default();
+        for (kind, value, max_failures) in self.limits(phone, ip) {
+            let attempts = match self.increment(&failure_key(kind, value)).await {
+                Ok(attempts) => attempts,
+                Err(e) => {
+                    error!("记录登录尝试次数失败: {}", e);
+                    continue;
+                },
+            };
+            if kind == "phone" {
+                attempt.phone_attempts = attempts;
+            } else {
+                attempt.ip_attempts = attempts;
+            }
+            if attempts > max_failures as u64 {
+                warn!("登录尝试次数超过上限，拒绝本次尝试: {}={}, 尝试次数={}", kind, value, attempts);
+                self.lock(kind, value).await;
+                return LoginCheck::Locked { retry_after: self.config.lockout_secs };
+            }
+        }
+        LoginCheck::Allowed(attempt)
+    }
+
+    // 本次尝试的密码错误，失败次数已在 begin_attempt 中计入，达到上限时锁定
+    // 返回本次失败应施加的响应延迟
+    pub async fn record_failure(&self, phone: &str, ip: &str, attempt: LoginAttempt) -> Duration {
+        if !self.config.enabled {
+            return Duration::ZERO;
+        }
+
+        let counts = [attempt.phone_attempts, attempt.ip_attempts];
+        for ((kind, value, max_failures), failures) in self.limits(phone, ip).into_iter().zip(counts) {
+            if failures >= max_failures as u64 {
+                warn!("登录失败次数过多，临时锁定: {}={}, 失败次数={}", kind, value, failures);
+                self.lock(kind, value).await;
+            }
+        }
+
+        self.delay_for(attempt.phone_attempts)
+    }
+
+    // 登录成功后清除该手机号的失败次数，并撤销本次尝试在IP上的计数
+    // IP此前的失败次数保留到窗口结束
+    pub async fn record_success(&self, phone: &str, ip: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Err(e) = self.remove(&failure_key("phone", phone)).await {
+            error!("清除登录失败次数失败: {}", e);
+        }
+        if let Err(e) = self.decrement(&failure_key("ip", ip)).await {
+            error!("撤销登录尝试次数失败: {}", e);
+        }
+    }
+
+    fn limits<'a>(&self, phone: &'a str, ip: &'a str) -> [(&'static str, &'a str, u32); 2] {
+        [
+            ("phone", phone, self.config.max_failures_per_phone),
+            ("ip", ip, self.config.max_failures_per_ip),
+        ]
+    }
+
+    // 手机号和IP中较长的剩余锁定时间，未锁定时为0
+    async fn lock_remaining(&self, phone: &str, ip: &str) -> u64 {
+        let now = unix_now();
+        let mut retry_after = 0;
+        for key in [lock_key("phone", phone), lock_key("ip", ip)] {
+            match self.get(&key).await {
+                Ok(Some(raw)) => {
+                    let locked_until = raw.parse::<u64>().unwrap_or(0);
+                    retry_after = retry_after.max(locked_until.saturating_sub(now));
+                },
+                Ok(None) => {},
+                Err(e) => error!("读取登录锁定状态失败: {}", e),
+            }
+        }
+        retry_after
+    }
+
+    async fn lock(&self, kind: &str, value: &str) {
+        let locked_until = unix_now() + self.config.lockout_secs;
+        if let Err(e) = self.set(&lock_key(kind, value), &locked_until.to_string(), self.config.lockout_secs).await {
+            error!("写入登录锁定状态失败: {}", e);
+        }
+    }
+
+    // 前几次失败不延迟，之后每次失败延迟翻倍，直到上限
+    fn delay_for(&self, failures: u64) -> Duration {
+        let free = self.config.delay_after_failures as u64;
+        if failures <= free {
+            return Duration::ZERO;
+        }
+        let exponent = (failures - free - 1).min(16) as u32;
+        let delay_ms = self.config.base_delay_ms.saturating_mul(1 << exponent).min(self.config.max_delay_ms);
+        Duration::from_millis(delay_ms)
+    }
+
+    // 获取Redis连接，连接池未初始化或连接失败时返回None并使用进程内缓存
+    // 登录限制故障时放行比拒绝所有登录更可取，因此这里不像令牌存储那样返回错误
+    async fn redis_connection(&self) -> Option<Connection> {
+        redis_pool::get_connection_or_fallback(&self.redis, "登录限制").await
+    }
+
+    // 尝试次数加一，窗口从第一次尝试开始计算
+    async fn increment(&self, key: &str) -> Result<u64, String> {
+        let window = self.config.failure_window_secs;
+        match self.redis_connection().await {
+            Some(mut conn) => redis_pool::incr_with_expiry(&mut conn, key, window)
+                .await
+                .map(|attempts| attempts.max(0) as u64)
+                .map_err(|e| e.to_string()),
+            // 进程内缓存的计数在第一次尝试时设置过期时间，之后递增保留原有的过期时间
+            None => self.cache.incr_by(key, 1, Some(window)).map(|attempts| attempts.max(0) as u64).map_err(String::from),
+        }
+    }
+
+    // 尝试次数减一，计数已过期时不做任何事，避免留下没有过期时间的负数计数
+    async fn decrement(&self, key: &str) -> Result<(), String> {
+        match self.redis_connection().await {
+            Some(mut conn) => redis_pool::decr_if_exists(&mut conn, key).await.map(|_| ()).map_err(|e| e.to_string()),
+            None => {
+                if self.cache.get(key)?.is_some() {
+                    self.cache.decr_by(key, 1, None)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        match self.redis_connection().await {
+            Some(mut conn) => redis_pool::get(&mut conn, key).await.map_err(|e| e.to_string()),
+            None => self.cache.get(key),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), String> {
+        match self.redis_connection().await {
+            Some(mut conn) => redis_pool::set_with_expiry(&mut conn, key, value, ttl_secs).await.map_err(|e| e.to_string()),
+            None => self.cache.set(key, value.to_string(), Some(ttl_secs)).map_err(String::from),
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), String> {
+        match self.redis_connection().await {
+            Some(mut conn) => redis_pool::del(&mut conn, key).await.map_err(|e| e.to_string()),
+            None => self.cache.remove(key).map(|_| ()),
+        }
+    }
+}
+
+fn failure_key(kind: &str, value: &str) -> String {
+    format!("{}{}:{}", FAILURES_PREFIX, kind, value)
+}
+
+fn lock_key(kind: &str, value: &str) -> String {
+    format!("{}{}:{}", LOCKED_PREFIX, kind, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use crate::config::RedisConfig;
+    use super::*;
+
+    fn test_throttle() -> Arc<LoginThrottle> {
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        Arc::new(LoginThrottle::new(Arc::new(None), cache, LoginThrottleConfig::default()))
+    }
+
+    // 连续失败直到被锁定
+    async fn fail_until_locked(throttle: &LoginThrottle, phone: &str, ip: &str) {
+        loop {
+            match throttle.begin_attempt(phone, ip).await {
+                LoginCheck::Allowed(attempt) => {
+                    throttle.record_failure(phone, ip, attempt).await;
+                },
+                LoginCheck::Locked { .. } => return,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lock_is_not_evicted_by_other_phones() {
+        let throttle = test_throttle();
+        fail_until_locked(&throttle, "13800000000", "10.0.0.1").await;
+
+        // 攻击者轮换大量手机号，写入远超默认缓存容量的计数
+        for i in 0..30_000 {
+            throttle.begin_attempt(&format!("139{:08}", i), &format!("10.1.{}.{}", i / 256, i % 256)).await;
+        }
+        assert!(matches!(throttle.begin_attempt("13800000000", "10.0.0.1").await, LoginCheck::Locked { .. }));
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_falls_back_to_local_counters() {
+        let redis = redis_pool::init_redis_pool(&RedisConfig {
+            url: String::from("redis://127.0.0.1:1/0"),
+            ..RedisConfig::default()
+        }).unwrap();
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        let throttle = LoginThrottle::new(redis, cache, LoginThrottleConfig::default());
+
+        // Redis不可用时限制按实例继续生效
+        let max = LoginThrottleConfig::default().max_failures_per_phone;
+        for _ in 0..max {
+            let LoginCheck::Allowed(attempt) = throttle.begin_attempt("13800000000", "10.0.0.1").await else {
+                panic!("attempt should be allowed");
+            };
+            throttle.record_failure("13800000000", "10.0.0.1", attempt).await;
+        }
+        assert!(matches!(throttle.begin_attempt("13800000000", "10.0.0.1").await, LoginCheck::Locked { .. }));
+    }
+
+    #[tokio::test]
+    async fn concurrent_attempts_are_counted_before_verification() {
+        let throttle = test_throttle();
+        let max = LoginThrottleConfig::default().max_failures_per_phone as usize;
+        let tasks: Vec<_> = (0..max * 4)
+            .map(|i| {
+                let throttle = throttle.clone();
+                tokio::spawn(async move { throttle.begin_attempt("13800000000", &format!("10.0.0.{}", i)).await })
+            })
+            .collect();
+        let mut allowed = 0;
+        for task in tasks {
+            if let LoginCheck::Allowed(_) = task.await.unwrap() {
+                allowed += 1;
+            }
+        }
+        assert_eq!(allowed, max);
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_phone_counter() {
+        let throttle = test_throttle();
+        for _ in 0..LoginThrottleConfig::default().max_failures_per_phone - 1 {
+            let LoginCheck::Allowed(attempt) = throttle.begin_attempt("13800000000", "10.0.0.1").await else {
+                panic!("attempt should be allowed");
+            };
+            throttle.record_failure("13800000000", "10.0.0.1", attempt).await;
+        }
+        let LoginCheck::Allowed(_) = throttle.begin_attempt("13800000000", "10.0.0.1").await else {
+            panic!("attempt should be allowed");
+        };
+        throttle.record_success("13800000000", "10.0.0.1").await;
+        let LoginCheck::Allowed(attempt) = throttle.begin_attempt("13800000000", "10.0.0.1").await else {
+            panic!("attempt should be allowed after a successful login");
+        };
+        assert_eq!(attempt.phone_attempts, 1);
+    }
+}
@@ -1,73 +1,160 @@
-use actix_web::{web, App, HttpServer, HttpRequest, middleware::Logger,dev,Result,middleware::ErrorHandlerResponse,middleware::ErrorHandlers};
+use actix_web::{web, App, HttpServer, middleware::Logger,dev,Result,middleware::ErrorHandlerResponse,middleware::ErrorHandlers};
 use actix_web::http::{header, StatusCode};
-use env_logger::Env;
 use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::time::Duration;
 
 // 引入我们拆分出去的模块
+mod config;
 mod db;
 mod routes;
 mod middleware;
 mod utils;
-mod cache;
+use test01::cache;
 mod redis_pool;
 // 添加 rbatis 模块
 mod rbatis_pool;
+mod password;
+mod token_store;
+mod api_keys;
+mod login_throttle;
+mod layered_cache;
 
 // 从middleware模块导入必要的类型
-use middleware::{JsonLogger, JsonLoggerConfig, LogLevel, JwtMiddleware, Claims};
+use middleware::{JsonLogger, LogLevel, JwtMiddleware};
+use middleware::public_routes::PublicRoutes;
+use middleware::jwt_keys::JwtKeySet;
 use serde_json::json;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // 加载配置，配置有误时直接退出
+    let app_config = match config::AppConfig::load() {
+        Ok(app_config) => config::init(app_config),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    
     // 初始化标准日志
     //env_logger::init_from_env(Env::default().default_filter_or("info"));
     // 配置日志,用于rbatis,可以打印mysql查询sql
     fast_log::init(fast_log::Config::new().console()).expect("rbatis初始化失败");
     // 初始化JSON日志器
     let json_logger = Arc::new(Mutex::new(
-        JsonLogger::new(JsonLoggerConfig::default())
+        JsonLogger::new(app_config.logger.clone())
             .expect("Failed to initialize JSON logger")
     ));
     
     // 记录服务器启动信息
     {
         let mut logger = json_logger.lock().unwrap();
-        logger.info("服务器开始初始化").unwrap();
+        logger.log_with_data(LogLevel::INFO, "服务器开始初始化", json!({"profile": app_config.profile.as_str()})).unwrap();
     }
     
-    // 注释掉原有的数据库连接池初始化代码
-    // let pool = match db::init_db_pool() {
-    //     Ok(pool) => {
-    //         // 记录数据库连接成功
-    //         {
-    //             let mut logger = json_logger.lock().unwrap();
-    //             logger.info("数据库连接池初始化成功").unwrap();
-    //         }
-    //         pool
-    //     },
-    //     Err(err) => {
-    //         // 记录数据库连接失败
-    //         {
-    //             let mut logger = json_logger.lock().unwrap();
-    //             let error_data = json!({"error": format!("{:?}", err)});
-    //             logger.log_with_data(LogLevel::FATAL, "数据库连接池初始化失败", error_data).unwrap();
-    //         }
-    //         eprintln!("Failed to initialize database pool: {:?}", err);
-    //         std::process::exit(1);
-    //     }
-    // };
+    // 初始化数据库连接池
+    let pool = match db::init_db_pool(&app_config.database) {
+        Ok(pool) => {
+            // 记录数据库连接成功
+            {
+                let mut logger = json_logger.lock().unwrap();
+                logger.info("数据库连接池初始化成功").unwrap();
+            }
+            pool
+        },
+        Err(err) => {
+            // 记录数据库连接失败
+            {
+                let mut logger = json_logger.lock().unwrap();
+                let error_data = json!({"error": format!("{:?}", err)});
+                logger.log_with_data(LogLevel::FATAL, "数据库连接池初始化失败", error_data).unwrap();
+            }
+            eprintln!("Failed to initialize database pool: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+    let app_data_pool = web::Data::new(pool);
+    if let Err(err) = rbatis_pool::apply_pool_config(&app_config.database).await {
+        json_logger.lock().unwrap().log_with_data(LogLevel::FATAL, "rbatis连接池初始化失败", json!({"error": err.to_string()})).unwrap();
+        eprintln!("Failed to initialize rbatis pool: {}", err);
+        std::process::exit(1);
+    }
     
     // 注册JSON日志器为应用数据
     let app_data_logger = web::Data::new(json_logger.clone());
     
-    // 初始化JWT中间件 - 实际应用中应该从环境变量读取密钥
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-default-secret-key-1234567890".to_string());
-    let jwt_middleware = JwtMiddleware::new(jwt_secret);
-    let app_data_jwt = web::Data::new(jwt_middleware.clone());
+    // 初始化JWT中间件
+    let public_routes = match PublicRoutes::from_config(&app_config.jwt.public_routes) {
+        Ok(public_routes) => public_routes,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let jwt_keys = match JwtKeySet::load(&app_config.jwt) {
+        Ok(jwt_keys) => jwt_keys,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
     
-    // 初始化缓存
-    let cache = cache::init_cache();
+    // 初始化密码哈希服务
+    let password_service = match password::PasswordService::new(&app_config.password) {
+        Ok(password_service) => password_service,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let app_data_password = web::Data::new(password_service);
+    
+    // 初始化缓存：cache 供 /cache 接口读写
+    // internal_cache 保存API密钥、两级缓存L1和GET响应等可以重新加载的内部状态，不通过任何接口暴露
+    // security_cache 保存令牌吊销记录和登录限制等安全状态，不设容量上限，缓存项只在过期后移除，避免被大量无关写入淘汰
+    let cache = cache::init_cache(&app_config.cache);
+    let internal_cache_config = cache::CacheConfig {
+        snapshot_path: app_config.cache.snapshot_path.as_ref().map(|path| format!("{}.internal", path)),
+        ..app_config.cache.clone()
+    };
+    let internal_cache = cache::init_cache(&internal_cache_config);
+    let security_cache_config = cache::CacheConfig {
+        shards: app_config.cache.shards,
+        sweep_interval_secs: app_config.cache.sweep_interval_secs,
+        snapshot_path: app_config.cache.snapshot_path.as_ref().map(|path| format!("{}.security", path)),
+        snapshot_interval_secs: app_config.cache.snapshot_interval_secs,
+        ..cache::CacheConfig::unbounded()
+    };
+    let security_cache = cache::init_cache(&security_cache_config);
     let app_data_cache = web::Data::new(cache.clone());
+    let mut snapshots = Vec::new();
+    let mut snapshotters = Vec::new();
+    let (snapshot_shutdown, _) = tokio::sync::watch::channel(false);
+    for (instance, config) in [
+        (&cache, &app_config.cache),
+        (&internal_cache, &internal_cache_config),
+        (&security_cache, &security_cache_config),
+    ] {
+        // 后台定期清理过期缓存项
+        cache::spawn_sweeper(instance.clone(), Duration::from_secs(config.sweep_interval_secs));
+        // 后台定期写入缓存快照
+        if let Some(path) = &config.snapshot_path {
+            let path = PathBuf::from(path);
+            snapshotters.push(cache::spawn_snapshotter(
+                instance.clone(),
+                path.clone(),
+                Duration::from_secs(config.snapshot_interval_secs),
+                snapshot_shutdown.subscribe(),
+            ));
+            snapshots.push((instance.clone(), path));
+        }
+    }
+    
+    let jwt_middleware = JwtMiddleware::new(jwt_keys)
+        .with_public_routes(public_routes)
+        .with_api_key_cache(internal_cache.clone());
+    let app_data_jwt = web::Data::new(jwt_middleware.clone());
     
     // 记录缓存初始化信息
     {
@@ -76,7 +163,7 @@ async fn main() -> std::io::Result<()> {
     }
     
     // 初始化Redis连接池
-    let redis_pool = match redis_pool::init_redis_pool() {
+    let redis_pool = match redis_pool::init_redis_pool(&app_config.redis) {
         Ok(redis_pool) => {
             // 记录Redis连接成功
             {
@@ -99,11 +186,43 @@ async fn main() -> std::io::Result<()> {
         }
     };
     
+    // 初始化令牌存储，未配置Redis时使用不淘汰的进程内缓存，配置了Redis时Redis故障会导致认证返回503
+    let token_store = token_store::TokenStore::new(
+        redis_pool.clone(),
+        security_cache.clone(),
+        Duration::from_secs(app_config.jwt.refresh_token_ttl_secs),
+    );
+    let app_data_token_store = web::Data::new(token_store);
+    
+    // 初始化登录失败限制，Redis不可用时使用不淘汰的进程内缓存，各实例分别计数
+    let login_throttle = login_throttle::LoginThrottle::new(
+        redis_pool.clone(),
+        security_cache.clone(),
+        app_config.login_throttle.clone(),
+    );
+    let app_data_login_throttle = web::Data::new(login_throttle);
+    
+    // 初始化限流器，各路由scope通过 RateLimit 中间件使用
+    let rate_limiter = middleware::rate_limit::RateLimiter::new(app_config.rate_limit.clone(), redis_pool.clone());
+    let app_data_rate_limiter = web::Data::new(rate_limiter);
+    
+    // 初始化两级缓存，业务数据通过它在多个实例之间共享
+    let layered_cache = layered_cache::LayeredCache::new(internal_cache.clone(), redis_pool.clone(), app_config.layered_cache.clone());
+    let app_data_layered_cache = web::Data::new(layered_cache);
+    // 初始化GET响应缓存，由路由上的 ResponseCache 中间件使用
+    let response_cache = middleware::response_cache::ResponseCacheStore::new(internal_cache.clone());
+    let app_data_response_cache = web::Data::new(response_cache);
+    
     // 注册Redis连接池作为应用数据
     let app_data_redis = web::Data::new(redis_pool);
     
     // 启动HTTP服务器
-    HttpServer::new(move || {
+    let bind_address = app_config.server.bind_address();
+    {
+        let mut logger = json_logger.lock().unwrap();
+        logger.log_with_data(LogLevel::INFO, "HTTP服务器启动", json!({"bind": bind_address, "workers": app_config.server.workers})).unwrap();
+    }
+    let mut server = HttpServer::new(move || {
         App::new()
             // 添加JWT中间件 - 放在错误处理中间件之前
             .wrap(jwt_middleware.clone())
@@ -117,22 +236,50 @@ async fn main() -> std::io::Result<()> {
                     .handler(StatusCode::NOT_FOUND, add_error_header)
                     .handler(StatusCode::UNAUTHORIZED, add_error_header)
             )
-            // 注释掉原有的数据库连接池注册
-            // .app_data(web::Data::new(pool.clone()))
+            // 注册数据库连接池作为应用数据
+            .app_data(app_data_pool.clone())
             // 注册JSON日志器作为应用数据
             .app_data(app_data_logger.clone())
             // 注册JWT中间件作为应用数据
             .app_data(app_data_jwt.clone())
+            // 注册密码哈希服务作为应用数据
+            .app_data(app_data_password.clone())
             // 注册缓存作为应用数据
             .app_data(app_data_cache.clone())
+            .app_data(app_data_layered_cache.clone())
+            .app_data(app_data_response_cache.clone())
             // 注册Redis连接池作为应用数据
             .app_data(app_data_redis.clone())
+            // 注册令牌存储作为应用数据
+            .app_data(app_data_token_store.clone())
+            .app_data(app_data_login_throttle.clone())
+            .app_data(app_data_rate_limiter.clone())
             // 配置路由
             .configure(routes::config)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    });
+    if let Some(workers) = app_config.server.workers {
+        server = server.workers(workers);
+    }
+    let result = server
+        .bind(bind_address)?
+        .run()
+        .await;
+
+    // 服务器正常关闭后先停止定期快照任务，等正在进行的写入完成后再写入最终的缓存快照
+    let _ = snapshot_shutdown.send(true);
+    for snapshotter in snapshotters {
+        if let Err(e) = snapshotter.await {
+            json_logger.lock().unwrap().log_with_data(LogLevel::ERROR, "缓存快照任务异常退出", json!({"error": e.to_string()})).unwrap();
+        }
+    }
+    for (instance, path) in &snapshots {
+        let mut logger = json_logger.lock().unwrap();
+        match instance.snapshot(path) {
+            Ok(count) => logger.log_with_data(LogLevel::INFO, "缓存快照已写入", json!({"path": path, "entries": count})).unwrap(),
+            Err(err) => logger.log_with_data(LogLevel::ERROR, "缓存快照写入失败", json!({"error": err})).unwrap(),
+        }
+    }
+    result
 }
 
 // 自定义一些错误头
@@ -0,0 +1,135 @@
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error, HttpMessage, HttpResponse, ResponseError};
+use actix_web::dev::{Transform, Service};
+use actix_web::http::StatusCode;
+use futures::future::{ok, ready, Either, Ready};
+use serde_json::json;
+use std::fmt;
+use std::task::{Context, Poll};
+use log::warn;
+use super::jwt::{AuthError, AuthenticatedUser};
+
+// 路由访问要求
+#[derive(Debug, Clone)]
+enum Requirement {
+    Role(String),
+    Permission(String),
+}
+
+impl Requirement {
+    fn is_satisfied_by(&self, user: &AuthenticatedUser) -> bool {
+        match self {
+            Requirement::Role(role) => user.has_role(role),
+            Requirement::Permission(permission) => user.has_permission(permission),
+        }
+    }
+}
+
+// 权限不足错误，返回结构化的403响应
+#[derive(Debug)]
+pub struct ForbiddenError {
+    requirement: Requirement,
+}
+
+impl fmt::Display for ForbiddenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.requirement {
+            Requirement::Role(role) => write!(f, "Missing required role '{}'", role),
+            Requirement::Permission(permission) => write!(f, "Missing required permission '{}'", permission),
+        }
+    }
+}
+
+impl ResponseError for ForbiddenError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let required = match &self.requirement {
+            Requirement::Role(role) => json!({"role": role}),
+            Requirement::Permission(permission) => json!({"permission": permission}),
+        };
+        HttpResponse::Forbidden().json(json!({
+            "status": "error",
+            "error": "forbidden",
+            "message": self.to_string(),
+            "required": required,
+        }))
+    }
+}
+
+// 路由级授权守卫，需配合JWT中间件使用
+// 使用方式: web::delete().to(handler).wrap(Authorize::permission("users:delete"))
+#[derive(Clone)]
+pub struct Authorize {
+    requirement: Requirement,
+}
+
+impl Authorize {
+    // 要求拥有指定权限
+    pub fn permission(permission: &str) -> Self {
+        Self { requirement: Requirement::Permission(permission.to_string()) }
+    }
+
+    // 要求拥有指定角色
+    pub fn role(role: &str) -> Self {
+        Self { requirement: Requirement::Role(role.to_string()) }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authorize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthorizeMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuthorizeMiddleware {
+            service,
+            requirement: self.requirement.clone(),
+        })
+    }
+}
+
+// 授权守卫的具体实现
+pub struct AuthorizeMiddleware<S> {
+    service: S,
+    requirement: Requirement,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let allowed = match req.extensions().get::<AuthenticatedUser>() {
+            Some(user) => self.requirement.is_satisfied_by(user),
+            None => {
+                return Either::Right(ready(Err(actix_web::error::ErrorUnauthorized(AuthError::MissingToken))));
+            },
+        };
+
+        if allowed {
+            Either::Left(self.service.call(req))
+        } else {
+            warn!("权限不足: {} {}, 需要: {:?}", req.method(), req.path(), self.requirement);
+            Either::Right(ready(Err(ForbiddenError { requirement: self.requirement.clone() }.into())))
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use actix_web::{web, HttpRequest, HttpResponse, Error, dev::{Service, ServiceRequest, ServiceResponse, Transform}};
+use actix_web::{web, Error, dev::{Service, ServiceRequest, ServiceResponse, Transform}};
 use futures::{Future, FutureExt}; 
 use std::pin::Pin; 
 use std::task::{Context, Poll}; 
 use std::fmt::Display; 
-use log::{error, warn, info}; 
+use log::{error, info}; 
 use backtrace::Backtrace;
 use serde_json::json; 
 use std::sync::{Arc, Mutex}; 
@@ -70,23 +70,22 @@ where
         let json_logger = req.app_data::<web::Data<Arc<Mutex<JsonLogger>>>>().cloned();
         
         // 如果有JSON日志器，记录请求信息
-        if let Some(logger) = &json_logger {
-            if let Ok(mut logger_guard) = logger.lock() {
-                let request_data = json!({
-                    "method": method.clone(),
-                    "path": path.clone(),
-                    "http_version": format!("{:?}", version),
-                    "headers": headers_clone.iter()
-                        .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
-                        .collect::<serde_json::Value>()
-                });
-                
-                let _ = logger_guard.log_with_data(
-                    LogLevel::INFO,
-                    "[ERROR MIDDLEWARE] 收到请求",
-                    request_data
-                );
-            }
+        if let Some(logger) = &json_logger
+            && let Ok(mut logger_guard) = logger.lock() {
+            let request_data = json!({
+                "method": method.clone(),
+                "path": path.clone(),
+                "http_version": format!("{:?}", version),
+                "headers": headers_clone.iter()
+                    .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
+                    .collect::<serde_json::Value>()
+            });
+            
+            let _ = logger_guard.log_with_data(
+                LogLevel::INFO,
+                "[ERROR MIDDLEWARE] 收到请求",
+                request_data
+            );
         }
         
         // 调用后续服务并处理结果 
@@ -122,15 +121,14 @@ where
                                method, path, status_code_num);
                         
                         // 如果有JSON日志器，记录错误状态码信息
-                        if let Some(logger) = &json_logger {
-                            if let Ok(mut logger_guard) = logger.lock() {
-                                let error_data = json!({"status_code": status_code_num, "status_text": status_code.canonical_reason()});
-                                let _ = logger_guard.log_with_data(
-                                    if status_code.is_server_error() { LogLevel::ERROR } else { LogLevel::WARNING }, 
-                                    &format!("[ERROR MIDDLEWARE] 捕获到错误响应: {} {} 状态码: {}", method, path, status_code_num), 
-                                    error_data
-                                );
-                            }
+                        if let Some(logger) = &json_logger
+                            && let Ok(mut logger_guard) = logger.lock() {
+                            let error_data = json!({"status_code": status_code_num, "status_text": status_code.canonical_reason()});
+                            let _ = logger_guard.log_with_data(
+                                if status_code.is_server_error() { LogLevel::ERROR } else { LogLevel::WARNING }, 
+                                &format!("[ERROR MIDDLEWARE] 捕获到错误响应: {} {} 状态码: {}", method, path, status_code_num), 
+                                error_data
+                            );
                         }
                     }
                     
@@ -147,15 +145,14 @@ where
                     error!("[ERROR MIDDLEWARE] 错误堆栈: {:?}", backtrace);
                     
                     // 如果有JSON日志器，记录错误信息
-                    if let Some(logger) = &json_logger {
-                        if let Ok(mut logger_guard) = logger.lock() {
-                            let error_data = json!({"error": format!("{:?}", err), "stack_trace": format!("{:?}", backtrace)});
-                            let _ = logger_guard.log_with_data(
-                                LogLevel::ERROR, 
-                                &format!("[ERROR MIDDLEWARE] 请求处理失败: {} {}", method, path), 
-                                error_data
-                            );
-                        }
+                    if let Some(logger) = &json_logger
+                        && let Ok(mut logger_guard) = logger.lock() {
+                        let error_data = json!({"error": format!("{:?}", err), "stack_trace": format!("{:?}", backtrace)});
+                        let _ = logger_guard.log_with_data(
+                            LogLevel::ERROR, 
+                            &format!("[ERROR MIDDLEWARE] 请求处理失败: {} {}", method, path), 
+                            error_data
+                        );
                     }
                     
                     // 返回错误响应 
@@ -167,6 +164,7 @@ where
 } 
 
 // 自定义错误类型，用于包装其他错误并提供更详细的上下文
+#[allow(dead_code)]
 #[derive(Debug)] 
 pub struct ApiError { 
     pub code: u16, 
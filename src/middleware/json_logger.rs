@@ -8,6 +8,7 @@ use std::sync::{Arc, Mutex};
 use std::fmt; 
 
 // 日志级别枚举
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     TRACE,
@@ -44,6 +45,8 @@ pub struct LogEntry {
 }
 
 // JSON日志器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct JsonLoggerConfig {
     pub log_dir: String,          // 日志目录
     pub max_file_size_mb: u64,    // 最大文件大小(MB)
@@ -106,19 +109,13 @@ impl JsonLogger {
         let mut max_index = 0;
 
         if let Ok(entries) = fs::read_dir(&self.config.log_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    if let Some(file_name) = entry.file_name().to_str() {
-                        if file_name.starts_with(&format!("{}-{}-", self.current_date, "app")) {
-                            if let Some(index_str) = file_name.strip_prefix(&format!("{}_{}_", self.current_date, "app")).and_then(|s| s.strip_suffix(".log")) {
-                                if let Ok(index) = index_str.parse::<u32>() {
-                                    if index > max_index {
-                                        max_index = index;
-                                    }
-                                }
-                            }
-                        }
-                    }
+            let prefix = format!("{}-{}-", self.current_date, "app");
+            for entry in entries.flatten() {
+                if let Some(file_name) = entry.file_name().to_str()
+                    && let Some(index_str) = file_name.strip_prefix(&prefix).and_then(|s| s.strip_suffix(".log"))
+                    && let Ok(index) = index_str.parse::<u32>()
+                    && index > max_index {
+                    max_index = index;
                 }
             }
         }
@@ -127,12 +124,11 @@ impl JsonLogger {
         
         // 检查当前文件大小，如果超过限制则创建新文件
         let file_path = self.get_log_file_path();
-        if Path::new(&file_path).exists() {
-            if let Ok(metadata) = fs::metadata(&file_path) {
-                let file_size_mb = metadata.len() / (1024 * 1024);
-                if file_size_mb >= self.config.max_file_size_mb {
-                    self.current_file_index += 1;
-                }
+        if Path::new(&file_path).exists()
+            && let Ok(metadata) = fs::metadata(&file_path) {
+            let file_size_mb = metadata.len() / (1024 * 1024);
+            if file_size_mb >= self.config.max_file_size_mb {
+                self.current_file_index += 1;
             }
         }
         
@@ -179,6 +175,20 @@ impl JsonLogger {
         Ok(())
     }
     
+    // 检查日志文件写入器是否可用，供健康检查使用
+    pub fn check_writer(&self) -> Result<(), io::Error> {
+        if !Path::new(&self.config.log_dir).is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("日志目录不存在: {}", self.config.log_dir)));
+        }
+        
+        let mut guard = self.file_writer.lock()
+            .map_err(|_| io::Error::other("日志写入器锁已损坏"))?;
+        match guard.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "日志文件写入器未初始化")),
+        }
+    }
+    
     // 记录日志
     pub fn log(&mut self, level: LogLevel, message: &str, module: Option<&str>, file: Option<&str>, line: Option<u32>, additional_data: Option<serde_json::Value>) -> Result<(), io::Error> {
         // 检查日志级别
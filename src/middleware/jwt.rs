@@ -1,75 +1,193 @@
-use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error, HttpMessage, web, HttpRequest}; 
+use actix_web::{dev::Payload, dev::ServiceRequest, dev::ServiceResponse, Error, FromRequest, HttpMessage, HttpRequest, web}; 
 use actix_web::dev::{Transform, Service}; 
-use futures::{future::{ok, Ready}, Future}; 
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation, errors::Error as JwtError}; 
+use futures::{future::{ok, ready, Ready}, Future}; 
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation, errors::Error as JwtError, errors::ErrorKind}; 
 use serde::{Deserialize, Serialize}; 
 use std::pin::Pin; 
+use std::rc::Rc; 
 use std::task::{Context, Poll}; 
-use std::time::{Duration, SystemTime, UNIX_EPOCH}; 
+use std::time::Duration; 
 use log::{info, error}; 
+use rand::RngCore; 
+use crate::token_store::TokenStore; 
+use crate::db::{DbPool, UserGrants}; 
+use crate::api_keys::{self, ApiKey, API_KEY_CACHE_NAMESPACE}; 
+use crate::cache::{Cache, LoadOptions};
+use crate::utils::time::unix_now_millis;
+use crate::utils::token::hash_token;
+use super::public_routes::PublicRoutes; 
+use super::jwt_keys::JwtKeySet; 
+
+// 服务间调用携带API密钥的请求头
+const API_KEY_HEADER: &str = "X-API-Key";
 
 // JWT声明结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // 用户名或用户ID
     pub user_id: u64, // 用户ID
     pub exp: u64, // 过期时间（UNIX时间戳）
     pub iat: u64, // 签发时间
+    #[serde(default)]
+    pub iat_ms: Option<u64>, // 签发时间（毫秒），用于判断是否早于“登出所有会话”
+    pub jti: String, // 令牌唯一ID，用于吊销
+    #[serde(default)]
+    pub roles: Vec<String>, // 用户角色
+    #[serde(default)]
+    pub permissions: Vec<String>, // 用户权限，'*' 表示全部权限
+}
+
+impl Claims {
+    // 签发时间（毫秒），没有 iat_ms 的旧令牌按 iat 所在秒的开始计算
+    pub fn issued_at_ms(&self) -> u64 {
+        self.iat_ms.unwrap_or(self.iat * 1000)
+    }
+}
+
+// 已认证的用户，由JWT中间件写入请求扩展
+// 可直接作为处理函数参数使用，未认证时自动返回401
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthenticatedUser {
+    pub id: u64,
+    pub subject: String, // 用户名（手机号）
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    pub token_id: String, // 访问令牌的 jti，API密钥认证时为密钥前缀
+    pub issued_at: u64,
+    pub expires_at: u64, // API密钥永不过期时为 u64::MAX
+    pub api_key_id: Option<u64>, // 通过API密钥认证时的密钥ID
+}
+
+impl AuthenticatedUser {
+    // 是否拥有指定角色
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+    
+    // 是否拥有指定权限
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == "*" || p == permission)
+    }
+}
+
+impl From<Claims> for AuthenticatedUser {
+    fn from(claims: Claims) -> Self {
+        Self {
+            id: claims.user_id,
+            subject: claims.sub,
+            roles: claims.roles,
+            permissions: claims.permissions,
+            token_id: claims.jti,
+            issued_at: claims.iat,
+            expires_at: claims.exp,
+            api_key_id: None,
+        }
+    }
+}
+
+// API密钥以其所属用户的身份执行，权限仅限于密钥的 scopes
+impl From<ApiKey> for AuthenticatedUser {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            id: api_key.owner_id,
+            subject: format!("api_key:{}", api_key.name),
+            roles: Vec::new(),
+            permissions: api_key.scopes,
+            token_id: api_key.key_prefix,
+            issued_at: api_key.created_at,
+            expires_at: api_key.expires_at.unwrap_or(u64::MAX),
+            api_key_id: Some(api_key.id),
+        }
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions()
+            .get::<AuthenticatedUser>()
+            .cloned()
+            .ok_or_else(|| actix_web::error::ErrorUnauthorized(AuthError::MissingToken)))
+    }
 }
 
 // JWT中间件配置
 #[derive(Clone)]
 pub struct JwtMiddleware {
-    secret_key: String, 
-    algorithm: Algorithm, 
+    keys: JwtKeySet, 
+    public_routes: PublicRoutes, // 无需认证的路由
+    api_key_cache: Option<Cache>, // API密钥查找结果的缓存，不能与 /cache 接口共用
 }
 
 impl Default for JwtMiddleware {
     fn default() -> Self {
         Self {
-            secret_key: "your-default-secret-key".to_string(), // 生产环境应该从环境变量读取
-            algorithm: Algorithm::HS256,
+            keys: JwtKeySet::from_secret("your-default-secret-key"), // 生产环境应该从环境变量读取
+            public_routes: PublicRoutes::new(),
+            api_key_cache: None,
         }
     }
 }
 
 impl JwtMiddleware {
     // 创建新的JWT中间件实例
-    pub fn new(secret_key: String) -> Self {
+    pub fn new(keys: JwtKeySet) -> Self {
         Self {
-            secret_key,
-            algorithm: Algorithm::HS256,
+            keys,
+            public_routes: PublicRoutes::new(),
+            api_key_cache: None,
         }
     }
     
+    // 设置免认证路由，未设置时所有请求都需要认证
+    // 也可以在单个scope上挂载带有不同路由列表的实例
+    pub fn with_public_routes(mut self, public_routes: PublicRoutes) -> Self {
+        self.public_routes = public_routes;
+        self
+    }
+    
+    // 设置缓存API密钥查找结果的内部缓存，未设置时每个请求都查询数据库
+    pub fn with_api_key_cache(mut self, cache: Cache) -> Self {
+        self.api_key_cache = Some(cache);
+        self
+    }
+    
     // 签发JWT令牌
-    pub fn generate_token(&self, user_id: u64, username: String, expires_in: Duration) -> Result<String, JwtError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs();
+    pub fn generate_token(&self, user_id: u64, username: String, grants: &UserGrants, expires_in: Duration) -> Result<String, JwtError> {
+        let now_ms = unix_now_millis();
+        let now = now_ms / 1000;
             
         let claims = Claims {
             sub: username,
             user_id,
             exp: now + expires_in.as_secs(),
             iat: now,
+            iat_ms: Some(now_ms),
+            jti: generate_jti(),
+            roles: grants.roles.clone(),
+            permissions: grants.permissions.clone(),
         };
         
-        encode(
-            &Header::new(self.algorithm),
-            &claims,
-            &EncodingKey::from_secret(self.secret_key.as_ref()),
-        )
+        let (kid, algorithm, encoding_key) = self.keys.signing_key();
+        let mut header = Header::new(algorithm);
+        header.kid = kid;
+        encode(&header, &claims, encoding_key)
     }
     
-    // 验证JWT令牌
+    // 验证JWT令牌，按令牌头中的 kid 选择密钥，算法以密钥配置为准
     pub fn validate_token(&self, token: &str) -> Result<Claims, JwtError> {
-        decode(
-            token, 
-            &DecodingKey::from_secret(self.secret_key.as_ref()), 
-            &Validation::new(self.algorithm),
-        ).map(|data| data.claims)
+        let header = decode_header(token)?;
+        let (algorithm, decoding_key) = self.keys
+            .decoding_key(header.kid.as_deref())
+            .ok_or(JwtError::from(ErrorKind::InvalidToken))?;
+        decode(token, decoding_key, &Validation::new(algorithm)).map(|data| data.claims)
+    }
+    
+    // 公开的 JWK Set
+    pub fn jwks(&self) -> serde_json::Value {
+        self.keys.jwks()
     }
     
     // 从请求头中提取JWT令牌
@@ -77,20 +195,62 @@ impl JwtMiddleware {
         req.headers()
             .get("Authorization")
             .and_then(|header| header.to_str().ok())
-            .and_then(|auth_header| {
-                if auth_header.starts_with("Bearer ") {
-                    Some(auth_header[7..].to_owned())
-                } else {
-                    None
-                }
-            })
+            .and_then(|auth_header| auth_header.strip_prefix("Bearer "))
+            .map(|token| token.to_owned())
+    }
+    
+    // 从请求头中提取API密钥
+    fn extract_api_key(&self, req: &ServiceRequest) -> Option<String> {
+        req.headers()
+            .get(API_KEY_HEADER)
+            .and_then(|header| header.to_str().ok())
+            .map(|key| key.trim().to_owned())
     }
 }
 
+// 生成128位随机令牌ID
+fn generate_jti() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// API密钥的缓存策略：内部缓存只保存密钥哈希到密钥ID的映射，该映射创建后不会改变
+// 吊销和过期状态每次都从数据库读取，吊销立即在所有实例生效
+const API_KEY_CACHE: LoadOptions = LoadOptions {
+    ttl: Some(300),
+    stale_while_revalidate: 0,
+    negative_ttl: Some(30), // 无效密钥同样缓存，避免无效请求反复查询数据库
+};
+
+// 校验API密钥，同一密钥的并发请求只按哈希查询一次数据库
+async fn authenticate_api_key(pool: web::Data<DbPool>, cache: Option<Cache>, key: String) -> Result<Option<ApiKey>, String> {
+    let cache_key = format!("{}:{}", API_KEY_CACHE_NAMESPACE, hash_token(&key));
+    let lookup_pool = pool.clone();
+    let load = move || async move {
+        let id = web::block(move || {
+            let mut conn = lookup_pool.get().map_err(|e| e.to_string())?;
+            api_keys::find_api_key_id(&mut conn, &key).map_err(|e| e.to_string())
+        }).await.map_err(|e| e.to_string())??;
+        Ok(id.map(|id| id.to_string()))
+    };
+    let id = match cache {
+        Some(cache) => cache.get_or_insert_with(&cache_key, API_KEY_CACHE, load).await?,
+        None => load().await?,
+    };
+    let Some(id) = id else { return Ok(None) };
+    let id: u64 = id.parse().map_err(|_| format!("缓存的API密钥ID无效: {}", id))?;
+
+    web::block(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        api_keys::load_active_api_key(&mut conn, id).map_err(|e| e.to_string())
+    }).await.map_err(|e| e.to_string())?
+}
+
 // 实现Transform trait，用于创建中间件
 impl<S, B> Transform<S, ServiceRequest> for JwtMiddleware 
 where 
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>, 
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static, 
     S::Future: 'static,
     B: 'static, 
 {
@@ -103,24 +263,22 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         info!("JWT中间件初始化完成");
         ok(JwtAuthMiddleware {
-            service,
-            secret_key: self.secret_key.clone(),
-            algorithm: self.algorithm,
+            service: Rc::new(service),
+            jwt: self.clone(),
         })
     }
 }
 
 // JWT认证中间件的具体实现
 pub struct JwtAuthMiddleware<S> {
-    service: S,
-    secret_key: String,
-    algorithm: Algorithm,
+    service: Rc<S>,
+    jwt: JwtMiddleware,
 }
 
 // 实现Service trait，处理请求
 impl<S, B> Service<ServiceRequest> for JwtAuthMiddleware<S> 
 where 
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>, 
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static, 
     S::Future: 'static,
     B: 'static, 
 {
@@ -136,31 +294,79 @@ where
         let path = req.path().to_string();
         let method = req.method().to_string();
         // 跳过认证的路径（如登录、注册、健康检查等）
-        if path.starts_with("/api/auth") || path.starts_with("/rbatis") || path == "/api/health" || path == "/api/logger"  || path == "/favicon.ico"{
-            let fut = self.service.call(req);
-            return Box::pin(async move { fut.await });
+        if self.jwt.public_routes.is_public(req.method(), &path) {
+            return Box::pin(self.service.call(req));
         }
         
-        // 创建JWT中间件实例用于验证
-        let jwt_middleware = JwtMiddleware::new(self.secret_key.clone());
+        // 携带API密钥的请求走API密钥认证
+        if let Some(api_key) = self.jwt.extract_api_key(&req) {
+            let pool = req.app_data::<web::Data<DbPool>>().cloned();
+            let cache = self.jwt.api_key_cache.clone();
+            let service = Rc::clone(&self.service);
+            
+            return Box::pin(async move {
+                let pool = match pool {
+                    Some(pool) => pool,
+                    None => return Err(actix_web::error::ErrorServiceUnavailable("Unable to verify API key")),
+                };
+                let result = authenticate_api_key(pool, cache, api_key).await;
+                
+                match result {
+                    Ok(Some(api_key)) => {
+                        req.extensions_mut().insert(AuthenticatedUser::from(api_key));
+                        service.call(req).await
+                    },
+                    Ok(None) => {
+                        error!("API密钥无效, 路径: {}, 方法: {}", path, method);
+                        Err(actix_web::error::ErrorUnauthorized(AuthError::InvalidApiKey))
+                    },
+                    Err(e) => {
+                        error!("校验API密钥失败: {}, 路径: {}, 方法: {}", e, path, method);
+                        Err(actix_web::error::ErrorServiceUnavailable("Unable to verify API key"))
+                    },
+                }
+            });
+        }
         
         // 提取并验证JWT令牌
-        match jwt_middleware.extract_token(&req) {
+        match self.jwt.extract_token(&req) {
             Some(token) => {
-                match jwt_middleware.validate_token(&token) {
+                match self.jwt.validate_token(&token) {
                     Ok(claims) => {
-                        // 将用户信息存储在请求扩展中，以便后续处理函数使用
-                        req.extensions_mut().insert(claims.user_id);
-                        req.extensions_mut().insert(claims.sub);
+                        let token_store = req.app_data::<web::Data<TokenStore>>().cloned();
+                        let service = Rc::clone(&self.service);
                         
-                        let fut = self.service.call(req);
-                        Box::pin(async move { fut.await })
+                        Box::pin(async move {
+                            // 检查令牌是否已被吊销（单个令牌登出或用户登出所有会话）
+                            if let Some(token_store) = token_store {
+                                match token_store.is_access_token_revoked(&claims.jti, claims.user_id, claims.issued_at_ms()).await {
+                                    Ok(false) => {},
+                                    Ok(true) => {
+                                        error!("JWT已被吊销: user_id={}, 路径: {}, 方法: {}", claims.user_id, path, method);
+                                        return Err(actix_web::error::ErrorUnauthorized(AuthError::RevokedToken));
+                                    },
+                                    Err(e) => {
+                                        error!("检查JWT吊销状态失败: {}, 路径: {}, 方法: {}", e, path, method);
+                                        return Err(actix_web::error::ErrorServiceUnavailable("Unable to verify token status"));
+                                    },
+                                }
+                            }
+                            
+                            // 将用户信息存储在请求扩展中，处理函数通过 AuthenticatedUser 提取
+                            req.extensions_mut().insert(AuthenticatedUser::from(claims));
+                            
+                            service.call(req).await
+                        })
                     },
                     Err(e) => {
                         error!("JWT验证失败: {}, 路径: {}, 方法: {}", e, path, method);
+                        let auth_error = match e.kind() {
+                            ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                            _ => AuthError::InvalidToken,
+                        };
                         // 返回错误，让Actix Web处理响应
                         Box::pin(async move { 
-                            Err(actix_web::error::ErrorUnauthorized("Invalid or expired token")) 
+                            Err(actix_web::error::ErrorUnauthorized(auth_error)) 
                         })
                     },
                 }
@@ -169,7 +375,7 @@ where
                 error!("未提供JWT令牌, 路径: {}, 方法: {}", path, method);
                 // 返回错误，让Actix Web处理响应
                 Box::pin(async move { 
-                    Err(actix_web::error::ErrorUnauthorized("Authorization token is missing")) 
+                    Err(actix_web::error::ErrorUnauthorized(AuthError::MissingToken)) 
                 })
             },
         }
@@ -182,6 +388,9 @@ pub enum AuthError {
     MissingToken,
     InvalidToken,
     ExpiredToken,
+    RevokedToken,
+    InvalidApiKey,
+    #[allow(dead_code)]
     Other(String),
 }
 
@@ -191,6 +400,8 @@ impl std::fmt::Display for AuthError {
             AuthError::MissingToken => write!(f, "Authorization token is missing"),
             AuthError::InvalidToken => write!(f, "Invalid token"),
             AuthError::ExpiredToken => write!(f, "Token has expired"),
+            AuthError::RevokedToken => write!(f, "Token has been revoked"),
+            AuthError::InvalidApiKey => write!(f, "Invalid, expired or revoked API key"),
             AuthError::Other(msg) => write!(f, "{}", msg),
         }
     }
@@ -198,11 +409,62 @@ impl std::fmt::Display for AuthError {
 
 impl std::error::Error for AuthError {}
 
-// 从请求中提取用户信息的辅助函数
-pub fn get_user_id_from_request(req: &HttpRequest) -> Option<u64> {
-    req.extensions().get::<u64>().copied()
-}
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App, HttpResponse};
+    use actix_web::dev::Service;
+    use actix_web::http::{Method, StatusCode};
+    use crate::config::JwtConfig;
+    use super::*;
 
-pub fn get_username_from_request(req: &HttpRequest) -> Option<String> {
-    req.extensions().get::<String>().cloned()
-}
\ No newline at end of file
+    // 按默认的免认证路由挂载JWT中间件，所有路径都由同一个处理函数返回200
+    async fn status_of(method: Method, path: &str) -> StatusCode {
+        let public_routes = PublicRoutes::from_config(&JwtConfig::default().public_routes).unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(JwtMiddleware::default().with_public_routes(public_routes))
+                .default_service(web::to(HttpResponse::Ok))
+        ).await;
+        let req = test::TestRequest::default().method(method).uri(path).to_request();
+        match app.call(req).await {
+            Ok(res) => res.status(),
+            Err(e) => e.as_response_error().status_code(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn default_public_routes_are_reachable_without_token() {
+        for (method, path) in [
+            (Method::POST, "/auth/login"),
+            (Method::POST, "/auth/register"),
+            (Method::POST, "/auth/refresh"),
+            (Method::GET, "/api/health"),
+            (Method::GET, "/api/health/ready"),
+            (Method::GET, "/rbatis/health"),
+            (Method::GET, "/.well-known/jwks.json"),
+        ] {
+            assert_eq!(status_of(method.clone(), path).await, StatusCode::OK, "{} {}", method, path);
+        }
+    }
+
+    #[actix_web::test]
+    async fn other_routes_require_token() {
+        for (method, path) in [
+            (Method::GET, "/auth/login"),
+            (Method::GET, "/auth/me"),
+            (Method::POST, "/auth/logout"),
+            (Method::GET, "/api/logger"),
+            (Method::GET, "/api/users"),
+            (Method::POST, "/api/users"),
+            (Method::GET, "/rbatis/users"),
+            (Method::POST, "/rbatis/users"),
+            (Method::PUT, "/rbatis/users/1"),
+            (Method::DELETE, "/rbatis/users/1"),
+            (Method::POST, "/rbatis/health"),
+            (Method::POST, "/cache/set"),
+            (Method::GET, "/admin/api-keys"),
+        ] {
+            assert_eq!(status_of(method.clone(), path).await, StatusCode::UNAUTHORIZED, "{} {}", method, path);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde_json::{json, Value};
+use simple_asn1::{from_der, oid, ASN1Block};
+use crate::config::{JwtConfig, JwtKeyConfig};
+
+// 密钥加载错误
+#[derive(Debug)]
+pub enum JwtKeyError {
+    Io(String, std::io::Error),        // 读取密钥文件失败
+    InvalidKey(String, String),        // 密钥格式错误
+    UnsupportedAlgorithm(String),
+    UnknownSigningKey(String),
+}
+
+impl fmt::Display for JwtKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtKeyError::Io(path, err) => write!(f, "读取JWT密钥文件 '{}' 失败: {}", path, err),
+            JwtKeyError::InvalidKey(kid, msg) => write!(f, "JWT密钥 '{}' 无效: {}", kid, msg),
+            JwtKeyError::UnsupportedAlgorithm(alg) => write!(f, "不支持的JWT签名算法 '{}'，可选值: RS256, ES256, EdDSA", alg),
+            JwtKeyError::UnknownSigningKey(kid) => write!(f, "jwt.signing_kid '{}' 未对应任何带私钥的密钥", kid),
+        }
+    }
+}
+
+impl std::error::Error for JwtKeyError {}
+
+// 单个签名密钥
+struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>, // 已退役的密钥只保留公钥，用于验证轮换前签发的令牌
+    decoding: DecodingKey,
+    jwk: Option<Value>,            // 对称密钥不公开
+}
+
+// JWT密钥集合：一个签名密钥，以及所有仍可用于验证的密钥（按 kid 区分）
+#[derive(Clone)]
+pub struct JwtKeySet {
+    keys: Arc<Vec<JwtKey>>,
+    signing: usize,
+}
+
+impl JwtKeySet {
+    // 使用共享密钥（HS256），令牌头中不带 kid
+    pub fn from_secret(secret: &str) -> Self {
+        let key = JwtKey {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret.as_bytes())),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        };
+        Self { keys: Arc::new(vec![key]), signing: 0 }
+    }
+
+    // 按配置加载密钥，未配置 jwt.keys 时退回 HS256 共享密钥
+    pub fn load(config: &JwtConfig) -> Result<Self, JwtKeyError> {
+        if config.keys.is_empty() {
+            return Ok(Self::from_secret(&config.secret));
+        }
+
+        let keys = config.keys.iter().map(load_key).collect::<Result<Vec<_>, _>>()?;
+        let signing_kid = config.signing_kid.clone().unwrap_or_default();
+        let signing = keys.iter()
+            .position(|key| key.kid.as_deref() == Some(signing_kid.as_str()) && key.encoding.is_some())
+            .ok_or(JwtKeyError::UnknownSigningKey(signing_kid))?;
+        Ok(Self { keys: Arc::new(keys), signing })
+    }
+
+    // 当前用于签发令牌的密钥
+    pub fn signing_key(&self) -> (Option<String>, Algorithm, &EncodingKey) {
+        let key = &self.keys[self.signing];
+        let encoding = key.encoding.as_ref().expect("签名密钥必须包含私钥");
+        (key.kid.clone(), key.algorithm, encoding)
+    }
+
+    // 按令牌头中的 kid 查找验证密钥
+    pub fn decoding_key(&self, kid: Option<&str>) -> Option<(Algorithm, &DecodingKey)> {
+        self.keys.iter()
+            .find(|key| key.kid.as_deref() == kid)
+            .map(|key| (key.algorithm, &key.decoding))
+    }
+
+    // 公开的 JWK Set，供其他服务验证本服务签发的令牌
+    pub fn jwks(&self) -> Value {
+        let keys: Vec<&Value> = self.keys.iter().filter_map(|key| key.jwk.as_ref()).collect();
+        json!({ "keys": keys })
+    }
+}
+
+fn parse_algorithm(value: &str) -> Result<Algorithm, JwtKeyError> {
+    match value {
+        "RS256" => Ok(Algorithm::RS256),
+        "ES256" => Ok(Algorithm::ES256),
+        "EdDSA" => Ok(Algorithm::EdDSA),
+        _ => Err(JwtKeyError::UnsupportedAlgorithm(value.to_string())),
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, JwtKeyError> {
+    std::fs::read(path).map_err(|e| JwtKeyError::Io(path.to_string(), e))
+}
+
+fn load_key(config: &JwtKeyConfig) -> Result<JwtKey, JwtKeyError> {
+    let algorithm = parse_algorithm(&config.algorithm)?;
+    let invalid = |e: jsonwebtoken::errors::Error| JwtKeyError::InvalidKey(config.kid.clone(), e.to_string());
+
+    let public_pem = read_file(&config.public_key_path)?;
+    let decoding = match algorithm {
+        Algorithm::RS256 => DecodingKey::from_rsa_pem(&public_pem),
+        Algorithm::ES256 => DecodingKey::from_ec_pem(&public_pem),
+        _ => DecodingKey::from_ed_pem(&public_pem),
+    }.map_err(invalid)?;
+
+    let encoding = match &config.private_key_path {
+        Some(path) => {
+            let private_pem = read_file(path)?;
+            Some(match algorithm {
+                Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+                Algorithm::ES256 => EncodingKey::from_ec_pem(&private_pem),
+                _ => EncodingKey::from_ed_pem(&private_pem),
+            }.map_err(invalid)?)
+        },
+        None => None,
+    };
+    if let Some(encoding) = &encoding {
+        verify_key_pair(algorithm, encoding, &decoding)
+            .map_err(|msg| JwtKeyError::InvalidKey(config.kid.clone(), msg))?;
+    }
+
+    let mut jwk = public_jwk(algorithm, &public_pem)
+        .map_err(|msg| JwtKeyError::InvalidKey(config.kid.clone(), msg))?;
+    jwk["kid"] = json!(config.kid);
+    jwk["use"] = json!("sig");
+    jwk["alg"] = json!(config.algorithm);
+
+    Ok(JwtKey {
+        kid: Some(config.kid.clone()),
+        algorithm,
+        encoding,
+        decoding,
+        jwk: Some(jwk),
+    })
+}
+
+// 用私钥签发一个探测令牌并用公钥验证，避免私钥与公钥不是同一对时签发的令牌全部无法验证
+fn verify_key_pair(algorithm: Algorithm, encoding: &EncodingKey, decoding: &DecodingKey) -> Result<(), String> {
+    let probe = jsonwebtoken::encode(&Header::new(algorithm), &json!({ "sub": "key-probe" }), encoding)
+        .map_err(|e| format!("私钥无法签名: {}", e))?;
+    let mut validation = Validation::new(algorithm);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    jsonwebtoken::decode::<Value>(&probe, decoding, &validation)
+        .map(|_| ())
+        .map_err(|_| String::from("私钥与公钥不匹配"))
+}
+
+// 从PEM公钥（SubjectPublicKeyInfo，RSA也可以是PKCS#1）中提取JWK参数
+fn public_jwk(algorithm: Algorithm, public_pem: &[u8]) -> Result<Value, String> {
+    let pem = pem::parse(public_pem).map_err(|e| e.to_string())?;
+    if pem.tag() == "RSA PUBLIC KEY" && algorithm == Algorithm::RS256 {
+        return rsa_jwk(pem.contents());
+    }
+    if pem.tag() != "PUBLIC KEY" {
+        return Err(format!("不支持的PEM类型 '{}'，需要 PUBLIC KEY", pem.tag()));
+    }
+
+    let blocks = from_der(pem.contents()).map_err(|e| e.to_string())?;
+    let (algorithm_id, key_bytes) = match blocks.as_slice() {
+        [ASN1Block::Sequence(_, items)] => match items.as_slice() {
+            [ASN1Block::Sequence(_, algorithm_id), ASN1Block::BitString(_, _, key_bytes)] => (algorithm_id, key_bytes),
+            _ => return Err(String::from("SubjectPublicKeyInfo 结构无效")),
+        },
+        _ => return Err(String::from("SubjectPublicKeyInfo 结构无效")),
+    };
+    let key_oid = match algorithm_id.first() {
+        Some(ASN1Block::ObjectIdentifier(_, key_oid)) => key_oid,
+        _ => return Err(String::from("缺少公钥算法标识")),
+    };
+
+    match algorithm {
+        Algorithm::RS256 if *key_oid == oid!(1, 2, 840, 113549, 1, 1, 1) => rsa_jwk(key_bytes),
+        Algorithm::ES256 if *key_oid == oid!(1, 2, 840, 10045, 2, 1) => {
+            let is_p256 = matches!(algorithm_id.get(1), Some(ASN1Block::ObjectIdentifier(_, curve)) if *curve == oid!(1, 2, 840, 10045, 3, 1, 7));
+            // 未压缩的椭圆曲线点: 0x04 || x || y
+            if !is_p256 || key_bytes.len() != 65 || key_bytes[0] != 0x04 {
+                return Err(String::from("ES256 需要未压缩的 P-256 公钥"));
+            }
+            Ok(json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": URL_SAFE_NO_PAD.encode(&key_bytes[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&key_bytes[33..]),
+            }))
+        },
+        Algorithm::EdDSA if *key_oid == oid!(1, 3, 101, 112) && key_bytes.len() == 32 => Ok(json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(key_bytes),
+        })),
+        _ => Err(String::from("公钥类型与配置的算法不匹配")),
+    }
+}
+
+// RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+fn rsa_jwk(der: &[u8]) -> Result<Value, String> {
+    let blocks = from_der(der).map_err(|e| e.to_string())?;
+    match blocks.as_slice() {
+        [ASN1Block::Sequence(_, items)] => match items.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => Ok(json!({
+                "kty": "RSA",
+                "n": URL_SAFE_NO_PAD.encode(n.to_bytes_be().1),
+                "e": URL_SAFE_NO_PAD.encode(e.to_bytes_be().1),
+            })),
+            _ => Err(String::from("RSA 公钥结构无效")),
+        },
+        _ => Err(String::from("RSA 公钥结构无效")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::jwk::Jwk;
+    use super::*;
+
+    // tests/fixtures/jwt 下的密钥只用于测试
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/jwt/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn key_config(kid: &str, algorithm: &str, private_key: Option<&str>, public_key: &str) -> JwtKeyConfig {
+        JwtKeyConfig {
+            kid: kid.to_string(),
+            algorithm: algorithm.to_string(),
+            private_key_path: private_key.map(fixture),
+            public_key_path: fixture(public_key),
+        }
+    }
+
+    fn jwt_config(keys: Vec<JwtKeyConfig>, signing_kid: &str) -> JwtConfig {
+        JwtConfig { keys, signing_kid: Some(signing_kid.to_string()), ..JwtConfig::default() }
+    }
+
+    // 用密钥集签名，再只用公开的 JWK 验证，确认提取出的 JWK 参数与私钥对应
+    fn assert_verifies_with_published_jwk(keys: &JwtKeySet) -> Value {
+        let (kid, algorithm, encoding) = keys.signing_key();
+        let mut header = Header::new(algorithm);
+        header.kid = kid.clone();
+        let token = jsonwebtoken::encode(&header, &json!({ "sub": "1", "exp": u64::MAX / 2 }), encoding).unwrap();
+
+        let jwks = keys.jwks();
+        let jwk_value = jwks["keys"].as_array().unwrap().iter()
+            .find(|jwk| jwk["kid"].as_str() == kid.as_deref())
+            .cloned()
+            .unwrap();
+        let jwk: Jwk = serde_json::from_value(jwk_value.clone()).unwrap();
+        let decoding = DecodingKey::from_jwk(&jwk).unwrap();
+        let claims = jsonwebtoken::decode::<Value>(&token, &decoding, &Validation::new(algorithm)).unwrap().claims;
+        assert_eq!(claims["sub"], "1");
+        jwk_value
+    }
+
+    #[test]
+    fn rsa_jwk_is_extracted_from_spki_and_pkcs1_public_keys() {
+        let spki = JwtKeySet::load(&jwt_config(vec![key_config("rsa", "RS256", Some("rsa.key.pem"), "rsa.pub.pem")], "rsa")).unwrap();
+        let jwk = assert_verifies_with_published_jwk(&spki);
+        assert_eq!(jwk["kty"], "RSA");
+        assert_eq!(jwk["e"], "AQAB");
+        assert_eq!(jwk["alg"], "RS256");
+        assert_eq!(jwk["use"], "sig");
+        // 2048 位模数，去掉 DER 整数的前导零后为 256 字节
+        assert_eq!(URL_SAFE_NO_PAD.decode(jwk["n"].as_str().unwrap()).unwrap().len(), 256);
+
+        let pkcs1 = JwtKeySet::load(&jwt_config(vec![key_config("rsa", "RS256", Some("rsa.key.pem"), "rsa.pkcs1.pub.pem")], "rsa")).unwrap();
+        assert_eq!(assert_verifies_with_published_jwk(&pkcs1), jwk);
+    }
+
+    #[test]
+    fn ec_and_ed25519_jwks_are_extracted_from_spki_public_keys() {
+        let ec = JwtKeySet::load(&jwt_config(vec![key_config("ec", "ES256", Some("ec.key.pem"), "ec.pub.pem")], "ec")).unwrap();
+        let jwk = assert_verifies_with_published_jwk(&ec);
+        assert_eq!(jwk["kty"], "EC");
+        assert_eq!(jwk["crv"], "P-256");
+        assert_eq!(URL_SAFE_NO_PAD.decode(jwk["x"].as_str().unwrap()).unwrap().len(), 32);
+        assert_eq!(URL_SAFE_NO_PAD.decode(jwk["y"].as_str().unwrap()).unwrap().len(), 32);
+
+        let ed = JwtKeySet::load(&jwt_config(vec![key_config("ed", "EdDSA", Some("ed25519.key.pem"), "ed25519.pub.pem")], "ed")).unwrap();
+        let jwk = assert_verifies_with_published_jwk(&ed);
+        assert_eq!(jwk["kty"], "OKP");
+        assert_eq!(jwk["crv"], "Ed25519");
+    }
+
+    #[test]
+    fn retired_public_keys_are_published_alongside_the_signing_key() {
+        let config = jwt_config(vec![
+            key_config("current", "ES256", Some("ec.key.pem"), "ec.pub.pem"),
+            key_config("retired", "RS256", None, "rsa.pub.pem"),
+        ], "current");
+        let keys = JwtKeySet::load(&config).unwrap();
+        let jwks = keys.jwks();
+        let kids: Vec<&str> = jwks["keys"].as_array().unwrap().iter().map(|jwk| jwk["kid"].as_str().unwrap()).collect();
+        assert_eq!(kids, vec!["current", "retired"]);
+        assert!(keys.decoding_key(Some("retired")).is_some());
+        assert!(matches!(
+            JwtKeySet::load(&jwt_config(config.keys.clone(), "retired")),
+            Err(JwtKeyError::UnknownSigningKey(_))
+        ));
+    }
+
+    #[test]
+    fn mismatched_key_pairs_are_rejected_at_load() {
+        let config = jwt_config(vec![key_config("rsa", "RS256", Some("rsa-other.key.pem"), "rsa.pub.pem")], "rsa");
+        match JwtKeySet::load(&config) {
+            Err(JwtKeyError::InvalidKey(kid, msg)) => {
+                assert_eq!(kid, "rsa");
+                assert!(msg.contains("不匹配"), "{}", msg);
+            },
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("mismatched key pair was accepted"),
+        }
+    }
+
+    #[test]
+    fn public_keys_of_another_algorithm_are_rejected() {
+        assert!(public_jwk(Algorithm::RS256, &std::fs::read(fixture("ec.pub.pem")).unwrap()).is_err());
+        assert!(public_jwk(Algorithm::ES256, &std::fs::read(fixture("ed25519.pub.pem")).unwrap()).is_err());
+        assert!(public_jwk(Algorithm::ES256, &std::fs::read(fixture("rsa.pkcs1.pub.pem")).unwrap()).is_err());
+        assert!(rsa_jwk(b"not der").is_err());
+    }
+}
@@ -2,8 +2,17 @@
 pub mod error_handler;
 pub mod json_logger;
 pub mod jwt;
+pub mod jwt_keys;
+pub mod authorization;
+pub mod public_routes;
+pub mod rate_limit;
+pub mod response_cache;
 
 // 重导出中间件以便更方便地使用
+#[allow(unused_imports)]
 pub use error_handler::{ErrorHandler, ApiError};
 pub use json_logger::{JsonLogger, JsonLoggerConfig, LogLevel};
-pub use jwt::{JwtMiddleware, Claims};
\ No newline at end of file
+pub use jwt::JwtMiddleware;
+pub use authorization::Authorize;
+pub use rate_limit::RateLimit;
+pub use response_cache::ResponseCache;
\ No newline at end of file
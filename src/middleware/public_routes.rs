@@ -0,0 +1,96 @@
+use actix_web::http::Method;
+use crate::config::PublicRouteConfig;
+use crate::utils::glob::glob_match;
+
+// 路径匹配方式
+#[derive(Debug, Clone)]
+enum PathMatcher {
+    Exact(String),
+    Prefix(String),
+    Glob(String),
+}
+
+impl PathMatcher {
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            PathMatcher::Exact(expected) => path == expected,
+            PathMatcher::Prefix(prefix) => path.starts_with(prefix.as_str()),
+            PathMatcher::Glob(pattern) => glob_match(pattern, path, '/'),
+        }
+    }
+}
+
+// 单条免认证规则，methods 为空时匹配所有请求方法
+#[derive(Debug, Clone)]
+pub struct PublicRoute {
+    matcher: PathMatcher,
+    methods: Vec<Method>,
+}
+
+impl PublicRoute {
+    // 精确匹配路径
+    pub fn exact(path: &str) -> Self {
+        Self { matcher: PathMatcher::Exact(path.to_string()), methods: Vec::new() }
+    }
+
+    // 匹配路径前缀
+    pub fn prefix(prefix: &str) -> Self {
+        Self { matcher: PathMatcher::Prefix(prefix.to_string()), methods: Vec::new() }
+    }
+
+    // 通配符匹配，`*` 匹配一段路径，`**` 匹配多段路径
+    pub fn glob(pattern: &str) -> Self {
+        Self { matcher: PathMatcher::Glob(pattern.to_string()), methods: Vec::new() }
+    }
+
+    // 限定请求方法
+    pub fn methods(mut self, methods: &[Method]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        (self.methods.is_empty() || self.methods.contains(method)) && self.matcher.matches(path)
+    }
+}
+
+// JWT中间件的免认证路由列表
+#[derive(Debug, Clone, Default)]
+pub struct PublicRoutes {
+    routes: Vec<PublicRoute>,
+}
+
+impl PublicRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 添加一条规则
+    pub fn add(mut self, route: PublicRoute) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    // 从配置构建，规则无效时返回错误描述
+    pub fn from_config(configs: &[PublicRouteConfig]) -> Result<Self, String> {
+        let mut public_routes = Self::new();
+        for config in configs {
+            let route = match (&config.path, &config.prefix, &config.glob) {
+                (Some(path), None, None) => PublicRoute::exact(path),
+                (None, Some(prefix), None) => PublicRoute::prefix(prefix),
+                (None, None, Some(pattern)) => PublicRoute::glob(pattern),
+                _ => return Err(String::from("每条免认证规则必须且只能设置 path、prefix、glob 中的一个")),
+            };
+            let methods = config.methods.iter()
+                .map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()).map_err(|_| format!("无效的请求方法: '{}'", m)))
+                .collect::<Result<Vec<_>, _>>()?;
+            public_routes = public_routes.add(route.methods(&methods));
+        }
+        Ok(public_routes)
+    }
+
+    // 判断请求是否免认证
+    pub fn is_public(&self, method: &Method, path: &str) -> bool {
+        self.routes.iter().any(|route| route.matches(method, path))
+    }
+}
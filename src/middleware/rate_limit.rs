@@ -0,0 +1,413 @@
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, web, Error, HttpMessage, HttpResponse, ResponseError};
+use actix_web::dev::{Transform, Service};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use deadpool_redis::Connection;
+use futures::future::{ok, Ready};
+use futures::Future;
+use log::{error, warn};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use crate::config::{RateLimitBackend, RateLimitConfig, RateLimitRule};
+use crate::redis_pool::{self, RedisPool};
+use crate::utils::time::unix_now;
+use super::jwt::AuthenticatedUser;
+
+// 存储键前缀
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+
+// 一次限流判断的结果，用于生成 RateLimit-* 响应头
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: u64,       // 距离当前窗口结束的秒数
+    pub window_secs: u64,
+}
+
+impl RateLimitDecision {
+    fn headers(&self) -> [(HeaderName, String); 4] {
+        [
+            (HeaderName::from_static("ratelimit-limit"), self.limit.to_string()),
+            (HeaderName::from_static("ratelimit-remaining"), self.remaining.to_string()),
+            (HeaderName::from_static("ratelimit-reset"), self.reset.to_string()),
+            (HeaderName::from_static("ratelimit-policy"), format!("{};w={}", self.limit, self.window_secs)),
+        ]
+    }
+}
+
+// 进程内的窗口计数
+struct WindowCounter {
+    window: u64,
+    count: u64,
+    previous: u64,   // 上一窗口的计数
+    expires_at: u64, // 超过该时间后计数不再有用，可以清理
+}
+
+// 进程内计数及其过期索引，每次请求只清理已到期的计数，不遍历全部计数
+#[derive(Default)]
+struct MemoryCounters {
+    counters: HashMap<String, WindowCounter>,
+    expiries: BTreeMap<u64, Vec<String>>, // 过期时间 -> 键，计数延期后旧的索引项在清理时跳过
+}
+
+impl MemoryCounters {
+    fn purge_expired(&mut self, now: u64) {
+        while let Some(entry) = self.expiries.first_entry() {
+            if *entry.key() > now {
+                break;
+            }
+            for key in entry.remove() {
+                if self.counters.get(&key).is_some_and(|counter| counter.expires_at <= now) {
+                    self.counters.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+// 滑动窗口限流器：按当前窗口计数加上一窗口计数的加权值估算最近一个窗口内的请求数
+// 存储出错时放行，避免限流组件故障导致服务不可用
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    redis: RedisPool,
+    memory: Mutex<MemoryCounters>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, redis: RedisPool) -> Self {
+        Self {
+            config,
+            redis,
+            memory: Mutex::new(MemoryCounters::default()),
+        }
+    }
+
+    // scope 对应的限流规则，未启用或未配置时返回None
+    fn rule(&self, scope: &str) -> Option<RateLimitRule> {
+        if !self.config.enabled {
+            return None;
+        }
+        self.config.scopes.get(scope).copied()
+    }
+
+    // 记录一次请求并判断是否超过限制
+    pub async fn hit(&self, scope: &str, client: &str, rule: RateLimitRule) -> Result<RateLimitDecision, String> {
+        self.hit_at(scope, client, rule, unix_now()).await
+    }
+
+    async fn hit_at(&self, scope: &str, client: &str, rule: RateLimitRule, now: u64) -> Result<RateLimitDecision, String> {
+        let window = now / rule.window_secs;
+        let key = format!("{}{}:{}", RATE_LIMIT_PREFIX, scope, client);
+        let (current, previous) = match self.redis_connection().await {
+            Some(mut conn) => self.hit_redis(&mut conn, &key, window, rule.window_secs).await?,
+            None => self.hit_memory(&key, now, window, rule.window_secs)?,
+        };
+
+        // 上一窗口的计数按其在滑动窗口中剩余的比例折算
+        let elapsed = now % rule.window_secs;
+        let weight = (rule.window_secs - elapsed) as f64 / rule.window_secs as f64;
+        let estimated = (previous as f64 * weight).floor() as u64 + current;
+        let limit = rule.requests as u64;
+        Ok(RateLimitDecision {
+            allowed: estimated <= limit,
+            limit: rule.requests,
+            remaining: limit.saturating_sub(estimated) as u32,
+            reset: rule.window_secs - elapsed,
+            window_secs: rule.window_secs,
+        })
+    }
+
+    // 获取Redis连接，使用进程内后端、连接池未初始化或连接失败时返回None
+    async fn redis_connection(&self) -> Option<Connection> {
+        if self.config.backend == RateLimitBackend::Memory {
+            return None;
+        }
+        redis_pool::get_connection_or_fallback(&self.redis, "限流").await
+    }
+
+    async fn hit_redis(&self, conn: &mut Connection, key: &str, window: u64, window_secs: u64) -> Result<(u64, u64), String> {
+        let current_key = format!("{}:{}", key, window);
+        // 保留两个窗口，下一窗口仍需要读取本窗口的计数
+        let current = redis_pool::incr_with_expiry(conn, &current_key, window_secs * 2).await.map_err(|e| e.to_string())?;
+        let previous = redis_pool::get(conn, &format!("{}:{}", key, window.saturating_sub(1)))
+            .await
+            .map_err(|e| e.to_string())?
+            .and_then(|raw| raw.parse::<u64>().ok())
+            .unwrap_or(0);
+        Ok((current.max(0) as u64, previous))
+    }
+
+    fn hit_memory(&self, key: &str, now: u64, window: u64, window_secs: u64) -> Result<(u64, u64), String> {
+        let mut memory = self.memory.lock().map_err(|e| format!("Failed to lock rate limiter: {:?}", e))?;
+        memory.purge_expired(now);
+
+        let memory = &mut *memory;
+        let counter = memory.counters.entry(key.to_string()).or_insert(WindowCounter { window, count: 0, previous: 0, expires_at: 0 });
+        if counter.window != window {
+            // 进入新窗口，原计数仅在相邻窗口时作为上一窗口的计数
+            counter.previous = if counter.window + 1 == window { counter.count } else { 0 };
+            counter.window = window;
+            counter.count = 0;
+        }
+        counter.count += 1;
+        let expires_at = (window + 2) * window_secs;
+        if counter.expires_at != expires_at {
+            counter.expires_at = expires_at;
+            memory.expiries.entry(expires_at).or_default().push(key.to_string());
+        }
+        Ok((counter.count, counter.previous))
+    }
+}
+
+// 超过限制时返回的429错误
+#[derive(Debug)]
+pub struct RateLimitExceeded {
+    decision: RateLimitDecision,
+}
+
+impl fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rate limit exceeded, retry in {} seconds", self.decision.reset)
+    }
+}
+
+impl ResponseError for RateLimitExceeded {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::TooManyRequests();
+        for header in self.decision.headers() {
+            response.insert_header(header);
+        }
+        response
+            .insert_header(("Retry-After", self.decision.reset.to_string()))
+            .json(json!({
+                "status": "error",
+                "error": "rate_limited",
+                "message": self.to_string(),
+                "retry_after": self.decision.reset,
+            }))
+    }
+}
+
+// 路由scope级限流中间件，需配合JWT中间件使用以便按用户计数
+// 使用方式: web::scope("/api").wrap(RateLimit::scope("api"))
+// 限流规则取自配置 rate_limit.scopes.<scope>，限流器通过 app_data 注册
+#[derive(Clone)]
+pub struct RateLimit {
+    scope: Rc<str>,
+}
+
+impl RateLimit {
+    pub fn scope(scope: &str) -> Self {
+        Self { scope: Rc::from(scope) }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            scope: self.scope.clone(),
+        })
+    }
+}
+
+// 限流中间件的具体实现
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    scope: Rc<str>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+        let rule = limiter.as_ref().and_then(|limiter| limiter.rule(&self.scope));
+        let (limiter, rule) = match (limiter, rule) {
+            (Some(limiter), Some(rule)) => (limiter, rule),
+            _ => return Box::pin(self.service.call(req)),
+        };
+
+        let service = Rc::clone(&self.service);
+        let scope = self.scope.clone();
+        let client = client_key(&req);
+        Box::pin(async move {
+            let decision = match limiter.hit(&scope, &client, rule).await {
+                Ok(decision) => decision,
+                Err(e) => {
+                    error!("限流计数失败，放行请求: {}, scope={}, client={}", e, scope, client);
+                    return service.call(req).await;
+                },
+            };
+            if !decision.allowed {
+                warn!("请求超过限流: scope={}, client={}, 路径: {}", scope, client, req.path());
+                return Err(RateLimitExceeded { decision }.into());
+            }
+
+            let mut res = service.call(req).await?;
+            for (name, value) in decision.headers() {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    res.headers_mut().insert(name, value);
+                }
+            }
+            Ok(res)
+        })
+    }
+}
+
+// 限流计数的调用方：API密钥、JWT用户，未认证时使用客户端IP
+fn client_key(req: &ServiceRequest) -> String {
+    if let Some(user) = req.extensions().get::<AuthenticatedUser>() {
+        return match user.api_key_id {
+            Some(api_key_id) => format!("api_key:{}", api_key_id),
+            None => format!("user:{}", user.id),
+        };
+    }
+    let ip = req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+    format!("ip:{}", ip)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::App;
+    use actix_web::http::header::RETRY_AFTER;
+    use crate::config::RateLimitConfig;
+    use super::*;
+
+    fn memory_limiter(requests: u32, window_secs: u64) -> RateLimiter {
+        let config = RateLimitConfig {
+            backend: RateLimitBackend::Memory,
+            scopes: HashMap::from([(String::from("api"), RateLimitRule { requests, window_secs })]),
+            ..RateLimitConfig::default()
+        };
+        RateLimiter::new(config, Arc::new(None))
+    }
+
+    async fn allowed_at(limiter: &RateLimiter, client: &str, now: u64) -> bool {
+        let rule = limiter.rule("api").unwrap();
+        limiter.hit_at("api", client, rule, now).await.unwrap().allowed
+    }
+
+    #[actix_web::test]
+    async fn requests_within_the_limit_are_allowed_and_the_next_is_rejected() {
+        let limiter = memory_limiter(3, 60);
+        let rule = limiter.rule("api").unwrap();
+        for remaining in [2, 1, 0] {
+            let decision = limiter.hit_at("api", "user:1", rule, 600).await.unwrap();
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+        let decision = limiter.hit_at("api", "user:1", rule, 600).await.unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.reset, 60);
+
+        // 其他调用方单独计数
+        assert!(allowed_at(&limiter, "user:2", 600).await);
+    }
+
+    #[actix_web::test]
+    async fn previous_window_is_weighted_as_the_window_slides() {
+        let limiter = memory_limiter(4, 60);
+        for _ in 0..4 {
+            assert!(allowed_at(&limiter, "user:1", 600).await);
+        }
+        assert!(!allowed_at(&limiter, "user:1", 630).await);
+
+        // 下一窗口过半时，上一窗口的5次请求折算为2次
+        assert!(allowed_at(&limiter, "user:1", 690).await);
+        assert!(allowed_at(&limiter, "user:1", 690).await);
+        assert!(!allowed_at(&limiter, "user:1", 690).await);
+
+        // 间隔超过一个窗口后，计数全部清零
+        let rule = limiter.rule("api").unwrap();
+        let decision = limiter.hit_at("api", "user:1", rule, 840).await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 3);
+    }
+
+    #[actix_web::test]
+    async fn expired_counters_are_purged_without_dropping_live_ones() {
+        let limiter = memory_limiter(10, 60);
+        for client in ["ip:1", "ip:2", "ip:3"] {
+            allowed_at(&limiter, client, 0).await;
+        }
+        // ip:1 在下一窗口继续请求，过期时间延后到180
+        allowed_at(&limiter, "ip:1", 60).await;
+
+        allowed_at(&limiter, "ip:4", 120).await;
+        let memory = limiter.memory.lock().unwrap();
+        let mut clients: Vec<&str> = memory.counters.keys().map(String::as_str).collect();
+        clients.sort();
+        assert_eq!(clients, vec!["rate_limit:api:ip:1", "rate_limit:api:ip:4"]);
+        assert!(memory.expiries.keys().all(|expires_at| *expires_at > 120));
+    }
+
+    #[actix_web::test]
+    async fn middleware_returns_429_with_retry_after() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(memory_limiter(2, 60)))
+                .service(web::scope("/api").wrap(RateLimit::scope("api")).default_service(web::to(HttpResponse::Ok)))
+                .default_service(web::to(HttpResponse::Ok)),
+        ).await;
+        let request = || actix_web::test::TestRequest::get()
+            .uri("/api/users")
+            .peer_addr("10.0.0.1:40000".parse().unwrap())
+            .to_request();
+
+        for remaining in ["1", "0"] {
+            let res = app.call(request()).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), remaining);
+            assert_eq!(res.headers().get("ratelimit-policy").unwrap(), "2;w=60");
+        }
+
+        let res = match app.call(request()).await {
+            Ok(_) => panic!("request over the limit was allowed"),
+            Err(e) => e.error_response(),
+        };
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = res.headers().get(RETRY_AFTER).unwrap().to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+        assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), "0");
+
+        // 未挂载限流的路由不受影响
+        let res = app.call(actix_web::test::TestRequest::get().uri("/health").peer_addr("10.0.0.1:40000".parse().unwrap()).to_request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,410 @@
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, web, Error, HttpMessage, HttpResponse};
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{Transform, Service};
+use actix_web::http::header::{self, HeaderMap, HeaderName};
+use actix_web::http::{Method, StatusCode};
+use futures::future::{ok, Ready};
+use futures::Future;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use crate::cache::Cache;
+use crate::utils::time::unix_now_millis;
+use super::jwt::AuthenticatedUser;
+
+// 缓存键前缀
+const RESPONSE_CACHE_PREFIX: &str = "http_cache:";
+// 默认缓存时间（秒）
+const DEFAULT_TTL_SECS: u64 = 30;
+// 不缓存的响应头：逐跳头、由响应体决定的长度、由中间件重新生成的头，以及不应在后续响应中重放的 Set-Cookie
+const UNCACHED_HEADERS: [HeaderName; 14] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    header::CONTENT_LENGTH,
+    header::ETAG,
+    header::CACHE_CONTROL,
+    header::SET_COOKIE,
+    header::DATE,
+    HeaderName::from_static("x-cache"),
+];
+// 304响应中保留的缓存响应头（RFC 9110 15.4.5）
+const NOT_MODIFIED_HEADERS: [HeaderName; 3] = [header::CONTENT_LOCATION, header::EXPIRES, header::VARY];
+
+// 缓存的GET响应
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    headers: Vec<(String, String)>,
+    body: String,
+    etag: String,
+}
+
+// 响应缓存存储，按分组记录代数，分组失效时代数加一，旧代数的缓存项不再被读取，由LRU和过期清理回收
+// 代数从启动时间开始计算，避免从快照恢复的旧缓存项在重启后重新生效
+// 失效只作用于当前实例，其他实例最迟在缓存过期后返回新的响应
+pub struct ResponseCacheStore {
+    cache: Cache,
+    epoch: u64,
+    generations: Mutex<HashMap<String, u64>>,
+}
+
+impl ResponseCacheStore {
+    pub fn new(cache: Cache) -> Self {
+        Self {
+            cache,
+            epoch: unix_now_millis(),
+            generations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn generation(&self, group: &str) -> u64 {
+        match self.generations.lock() {
+            Ok(generations) => generations.get(group).copied().unwrap_or(self.epoch),
+            Err(e) => {
+                error!("Failed to lock response cache generations: {:?}", e);
+                self.epoch
+            },
+        }
+    }
+
+    // 使分组内所有缓存的响应失效
+    pub fn invalidate(&self, group: &str) {
+        match self.generations.lock() {
+            Ok(mut generations) => {
+                let generation = generations.entry(group.to_string()).or_insert(self.epoch);
+                *generation += 1;
+            },
+            Err(e) => error!("Failed to lock response cache generations: {:?}", e),
+        }
+    }
+}
+
+// 路由级响应缓存，需配合JWT中间件使用，缓存按用户区分
+// GET请求：缓存200响应，计算强ETag，If-None-Match匹配时返回304
+// 其他请求：成功（2xx）后使同一分组的缓存失效
+// 使用方式: web::get().to(handler).wrap(ResponseCache::group("users").ttl(30))
+// 缓存存储通过 app_data 注册，未注册时不缓存
+#[derive(Clone)]
+pub struct ResponseCache {
+    group: Rc<str>,
+    ttl: u64,
+}
+
+impl ResponseCache {
+    pub fn group(group: &str) -> Self {
+        Self { group: Rc::from(group), ttl: DEFAULT_TTL_SECS }
+    }
+
+    // 设置缓存时间（秒），同时作为 Cache-Control 的 max-age
+    pub fn ttl(mut self, ttl: u64) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ResponseCache
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResponseCacheMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ResponseCacheMiddleware {
+            service: Rc::new(service),
+            group: self.group.clone(),
+            ttl: self.ttl,
+        })
+    }
+}
+
+// 响应缓存中间件的具体实现
+pub struct ResponseCacheMiddleware<S> {
+    service: Rc<S>,
+    group: Rc<str>,
+    ttl: u64,
+}
+
+impl<S, B> Service<ServiceRequest> for ResponseCacheMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let group = self.group.clone();
+        let Some(store) = req.app_data::<web::Data<ResponseCacheStore>>().cloned() else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_boxed_body()) });
+        };
+
+        if req.method() != Method::GET {
+            return Box::pin(async move {
+                let res = service.call(req).await?;
+                if res.status().is_success() {
+                    store.invalidate(&group);
+                }
+                Ok(res.map_into_boxed_body())
+            });
+        }
+
+        let ttl = self.ttl;
+        let key = cache_key(&req, &group, store.generation(&group));
+        let if_none_match = req.headers().get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        Box::pin(async move {
+            let cached = store.cache.get_typed::<CachedResponse>(&key).unwrap_or_else(|e| {
+                warn!("读取响应缓存失败: {}", e);
+                None
+            });
+            if let Some(cached) = cached {
+                let response = cached_response(cached, if_none_match.as_deref(), ttl, "HIT");
+                return Ok(req.into_response(response));
+            }
+
+            let res = service.call(req).await?;
+            if res.status() != StatusCode::OK {
+                return Ok(res.map_into_boxed_body());
+            }
+
+            // 读取响应体以计算ETag并写入缓存
+            let (req, res) = res.into_parts();
+            let headers = cacheable_headers(res.headers());
+            let (head, body) = res.into_parts();
+            let bytes = body::to_bytes(body)
+                .await
+                .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+            let (headers, body) = match (headers, std::str::from_utf8(&bytes)) {
+                (Some(headers), Ok(body)) => (headers, body.to_string()),
+                // 非文本响应或响应头含有非ASCII值时不缓存
+                _ => return Ok(ServiceResponse::new(req, head.set_body(bytes).map_into_boxed_body())),
+            };
+
+            let cached = CachedResponse { etag: etag_for(&body), headers, body };
+            if let Err(e) = store.cache.set_typed(&key, &cached, Some(ttl)) {
+                warn!("写入响应缓存失败: {}", e);
+            }
+            let response = cached_response(cached, if_none_match.as_deref(), ttl, "MISS");
+            Ok(ServiceResponse::new(req, response))
+        })
+    }
+}
+
+// 缓存键包含分组代数、调用方、路径和查询参数
+fn cache_key(req: &ServiceRequest, group: &str, generation: u64) -> String {
+    let caller = match req.extensions().get::<AuthenticatedUser>() {
+        Some(user) => match user.api_key_id {
+            Some(api_key_id) => format!("api_key:{}", api_key_id),
+            None => format!("user:{}", user.id),
+        },
+        None => String::from("anonymous"),
+    };
+    format!("{}{}:{}:{}:{}?{}", RESPONSE_CACHE_PREFIX, group, generation, caller, req.path(), req.query_string())
+}
+
+// 需要缓存的响应头，Connection 中列出的头同样是逐跳头；有无法表示为字符串的值时返回None
+fn cacheable_headers(headers: &HeaderMap) -> Option<Vec<(String, String)>> {
+    let connection_headers: Vec<String> = headers.get_all(header::CONNECTION)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    headers.iter()
+        .filter(|(name, _)| !UNCACHED_HEADERS.contains(name) && !connection_headers.iter().any(|hop| hop == name.as_str()))
+        .map(|(name, value)| value.to_str().ok().map(|value| (name.to_string(), value.to_string())))
+        .collect()
+}
+
+// 强ETag：响应体SHA-256的前128位
+fn etag_for(body: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(body.as_bytes()));
+    format!("\"{}\"", &digest[..32])
+}
+
+// If-None-Match 可以是 * 或逗号分隔的ETag列表
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+fn cached_response(cached: CachedResponse, if_none_match: Option<&str>, ttl: u64, status: &str) -> HttpResponse {
+    let not_modified = if_none_match.is_some_and(|value| etag_matches(value, &cached.etag));
+    let mut builder = if not_modified { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+    // 响应按用户缓存，不允许共享缓存保存
+    builder
+        .insert_header((header::ETAG, cached.etag))
+        .insert_header((header::CACHE_CONTROL, format!("private, max-age={}", ttl)))
+        .insert_header(("X-Cache", status));
+    for (name, value) in cached.headers {
+        if !not_modified || NOT_MODIFIED_HEADERS.iter().any(|kept| kept.as_str() == name) {
+            builder.append_header((name, value));
+        }
+    }
+    if not_modified {
+        return builder.finish();
+    }
+    builder.body(cached.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use actix_web::App;
+    use actix_web::http::header::HeaderValue;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use super::*;
+
+    // 每次调用处理函数返回不同的响应体，用于判断是否命中缓存
+    async fn list_items(calls: web::Data<AtomicUsize>) -> HttpResponse {
+        let calls = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        HttpResponse::Ok()
+            .insert_header(("X-Total-Count", "2"))
+            .insert_header((header::VARY, "Accept-Language"))
+            .insert_header(("Keep-Alive", "timeout=5"))
+            .insert_header((header::CONNECTION, "X-Hop"))
+            .insert_header(("X-Hop", "1"))
+            .insert_header((header::SET_COOKIE, "session=abc"))
+            .json(serde_json::json!({ "items": ["a", "b"], "calls": calls }))
+    }
+
+    async fn create_item(body: String) -> HttpResponse {
+        if body.is_empty() { HttpResponse::BadRequest().finish() } else { HttpResponse::Created().finish() }
+    }
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::new(ResponseCacheStore::new(Arc::new(SimpleCache::new(CacheConfig::unbounded())))))
+            .route("/items", web::get().to(list_items).wrap(ResponseCache::group("items").ttl(60)))
+            .route("/items", web::post().to(create_item).wrap(ResponseCache::group("items")));
+    }
+
+    fn get(if_none_match: Option<&str>) -> actix_web::test::TestRequest {
+        let mut request = actix_web::test::TestRequest::get().uri("/items");
+        if let Some(etag) = if_none_match {
+            request = request.insert_header((header::IF_NONE_MATCH, etag));
+        }
+        request
+    }
+
+    fn post(body: &'static str) -> actix_web::test::TestRequest {
+        actix_web::test::TestRequest::post().uri("/items").set_payload(body)
+    }
+
+    fn header_value<B>(res: &ServiceResponse<B>, name: &str) -> Option<String> {
+        res.headers().get(name).map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[actix_web::test]
+    async fn cached_responses_replay_end_to_end_headers_only() {
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = actix_web::test::init_service(App::new().app_data(calls.clone()).configure(routes)).await;
+
+        let miss = actix_web::test::call_service(&app, get(None).to_request()).await;
+        assert_eq!(header_value(&miss, "x-cache").as_deref(), Some("MISS"));
+        let miss_body = actix_web::test::read_body(miss).await;
+
+        let hit = actix_web::test::call_service(&app, get(None).to_request()).await;
+        assert_eq!(hit.status(), StatusCode::OK);
+        assert_eq!(header_value(&hit, "x-cache").as_deref(), Some("HIT"));
+        assert_eq!(header_value(&hit, "content-type").as_deref(), Some("application/json"));
+        assert_eq!(header_value(&hit, "x-total-count").as_deref(), Some("2"));
+        assert_eq!(header_value(&hit, "vary").as_deref(), Some("Accept-Language"));
+        assert_eq!(header_value(&hit, "cache-control").as_deref(), Some("private, max-age=60"));
+        for hop_by_hop in ["keep-alive", "connection", "x-hop", "set-cookie"] {
+            assert!(hit.headers().get(hop_by_hop).is_none(), "{} was replayed", hop_by_hop);
+        }
+        assert_eq!(actix_web::test::read_body(hit).await, miss_body);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn etag_is_derived_from_the_body_and_if_none_match_returns_304() {
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = actix_web::test::init_service(App::new().app_data(calls.clone()).configure(routes)).await;
+
+        let res = actix_web::test::call_service(&app, get(None).to_request()).await;
+        let etag = header_value(&res, "etag").unwrap();
+        let body = actix_web::test::read_body(res).await;
+        assert_eq!(etag, etag_for(std::str::from_utf8(&body).unwrap()));
+
+        for if_none_match in [etag.clone(), format!("W/{}", etag), format!("\"other\", {}", etag), String::from("*")] {
+            let res = actix_web::test::call_service(&app, get(Some(&if_none_match)).to_request()).await;
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED, "{}", if_none_match);
+            assert_eq!(header_value(&res, "etag").as_deref(), Some(etag.as_str()));
+            assert_eq!(header_value(&res, "vary").as_deref(), Some("Accept-Language"));
+            assert!(res.headers().get("x-total-count").is_none());
+            assert!(actix_web::test::read_body(res).await.is_empty());
+        }
+
+        let res = actix_web::test::call_service(&app, get(Some("\"other\"")).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn successful_writes_invalidate_the_group() {
+        let calls = web::Data::new(AtomicUsize::new(0));
+        let app = actix_web::test::init_service(App::new().app_data(calls.clone()).configure(routes)).await;
+
+        let etag = header_value(&actix_web::test::call_service(&app, get(None).to_request()).await, "etag").unwrap();
+
+        // 失败的写请求不影响缓存
+        assert_eq!(actix_web::test::call_service(&app, post("").to_request()).await.status(), StatusCode::BAD_REQUEST);
+        let res = actix_web::test::call_service(&app, get(None).to_request()).await;
+        assert_eq!(header_value(&res, "x-cache").as_deref(), Some("HIT"));
+
+        assert_eq!(actix_web::test::call_service(&app, post("item").to_request()).await.status(), StatusCode::CREATED);
+        let res = actix_web::test::call_service(&app, get(Some(&etag)).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(header_value(&res, "x-cache").as_deref(), Some("MISS"));
+        assert_ne!(header_value(&res, "etag").unwrap(), etag);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn headers_listed_in_connection_are_not_cached() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("close, X-Trace"));
+        headers.insert(HeaderName::from_static("x-trace"), HeaderValue::from_static("1"));
+        headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static("zh-CN"));
+        headers.append(header::LINK, HeaderValue::from_static("</a>; rel=next"));
+        headers.append(header::LINK, HeaderValue::from_static("</b>; rel=last"));
+
+        let mut cached = cacheable_headers(&headers).unwrap();
+        cached.sort();
+        assert_eq!(cached, vec![
+            (String::from("content-language"), String::from("zh-CN")),
+            (String::from("link"), String::from("</a>; rel=next")),
+            (String::from("link"), String::from("</b>; rel=last")),
+        ]);
+
+        headers.insert(header::CONTENT_DISPOSITION, HeaderValue::from_bytes("attachment; filename=\"数据.csv\"".as_bytes()).unwrap());
+        assert!(cacheable_headers(&headers).is_none());
+    }
+}
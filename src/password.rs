@@ -0,0 +1,159 @@
+use std::fmt;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version};
+use argon2::password_hash::{PasswordHasher, SaltString, rand_core::OsRng};
+use crate::config::PasswordConfig;
+
+// argon2 哈希串的前缀，用于区分旧的明文密码
+const ARGON2_PREFIX: &str = "$argon2";
+
+// 密码处理错误
+#[derive(Debug)]
+pub enum PasswordError {
+    InvalidParams(String),
+    Hash(String),
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::InvalidParams(msg) => write!(f, "密码哈希参数无效: {}", msg),
+            PasswordError::Hash(msg) => write!(f, "密码哈希失败: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+// 密码校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verification {
+    pub valid: bool,
+    pub needs_rehash: bool, // 密码正确但存储格式或参数已过期，需要重新哈希
+}
+
+// 密码哈希服务，使用 argon2id
+#[derive(Clone)]
+pub struct PasswordService {
+    params: Params,
+    dummy_hash: String, // 用当前参数哈希的随机密码，用户不存在时代替真实哈希参与校验
+}
+
+impl PasswordService {
+    // 根据配置创建密码哈希服务
+    pub fn new(config: &PasswordConfig) -> Result<Self, PasswordError> {
+        let params = Params::new(config.memory_kib, config.iterations, config.parallelism, None)
+            .map_err(|e| PasswordError::InvalidParams(e.to_string()))?;
+        let mut service = Self { params, dummy_hash: String::new() };
+        service.dummy_hash = service.hash(SaltString::generate(&mut OsRng).as_ref())?;
+        Ok(service)
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    // 对明文密码进行哈希，返回 PHC 格式字符串
+    pub fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| PasswordError::Hash(e.to_string()))
+    }
+
+    // 校验密码
+    // 存储值不是 argon2 哈希时按旧的明文密码比较，校验通过后要求重新哈希
+    pub fn verify(&self, password: &str, stored: &str) -> Verification {
+        if !stored.starts_with(ARGON2_PREFIX) {
+            return Verification {
+                valid: constant_time_eq(password.as_bytes(), stored.as_bytes()),
+                needs_rehash: true,
+            };
+        }
+
+        let parsed = match PasswordHash::new(stored) {
+            Ok(parsed) => parsed,
+            Err(_) => return Verification { valid: false, needs_rehash: false },
+        };
+        let valid = self.argon2().verify_password(password.as_bytes(), &parsed).is_ok();
+        Verification {
+            valid,
+            needs_rehash: valid && self.is_outdated(&parsed),
+        }
+    }
+
+    // 用户不存在时调用：对占位哈希完整校验一次后返回失败
+    // 不存在的手机号与密码错误耗时相同，无法通过响应时间判断手机号是否已注册
+    pub fn verify_unknown_user(&self, password: &str) -> Verification {
+        let _ = self.verify(password, &self.dummy_hash);
+        Verification { valid: false, needs_rehash: false }
+    }
+
+    // 判断哈希的算法、版本或参数是否与当前配置不一致
+    fn is_outdated(&self, parsed: &PasswordHash<'_>) -> bool {
+        if parsed.algorithm != Algorithm::Argon2id.ident() || parsed.version != Some(Version::V0x13.into()) {
+            return true;
+        }
+        match Params::try_from(parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            },
+            Err(_) => true,
+        }
+    }
+}
+
+// 常量时间比较，避免通过耗时推断明文密码
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> PasswordConfig {
+        PasswordConfig { memory_kib: 64, iterations: 1, parallelism: 1 }
+    }
+
+    #[test]
+    fn current_hashes_verify_without_rehash() {
+        let service = PasswordService::new(&test_config()).unwrap();
+        let stored = service.hash("secret").unwrap();
+        assert_eq!(service.verify("secret", &stored), Verification { valid: true, needs_rehash: false });
+        assert_eq!(service.verify("wrong", &stored), Verification { valid: false, needs_rehash: false });
+    }
+
+    #[test]
+    fn legacy_plaintext_passwords_verify_and_require_rehash() {
+        let service = PasswordService::new(&test_config()).unwrap();
+        assert_eq!(service.verify("secret", "secret"), Verification { valid: true, needs_rehash: true });
+        assert!(!service.verify("secret2", "secret").valid);
+        assert!(!service.verify("", "secret").valid);
+    }
+
+    #[test]
+    fn hashes_with_outdated_params_require_rehash() {
+        let old = PasswordService::new(&test_config()).unwrap();
+        let stored = old.hash("secret").unwrap();
+        let service = PasswordService::new(&PasswordConfig { iterations: 2, ..test_config() }).unwrap();
+        assert_eq!(service.verify("secret", &stored), Verification { valid: true, needs_rehash: true });
+        // 密码错误时不要求重新哈希
+        assert_eq!(service.verify("wrong", &stored), Verification { valid: false, needs_rehash: false });
+    }
+
+    #[test]
+    fn unknown_users_are_verified_against_a_current_hash() {
+        let service = PasswordService::new(&test_config()).unwrap();
+        // 占位哈希使用当前参数，校验开销与真实用户相同
+        let parsed = PasswordHash::new(&service.dummy_hash).unwrap();
+        assert!(!service.is_outdated(&parsed));
+        assert!(!service.verify_unknown_user("secret").valid);
+        assert!(!service.verify_unknown_user("").valid);
+    }
+}
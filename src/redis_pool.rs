@@ -1,6 +1,8 @@
 use std::sync::Arc;
 use actix_web::{web, error, Error};
-use deadpool_redis::{Config, Pool, Connection, Runtime, redis::cmd};
+use deadpool_redis::{Config, Pool, PoolConfig, Connection, Runtime, redis::cmd};
+use log::error;
+use crate::config::RedisConfig;
 
 // Redis连接池类型别名
 type RedisPoolInner = Pool;
@@ -22,13 +24,31 @@ pub async fn get_redis_connection_or_return_error(
     }
 }
 
+// 获取Redis连接，连接池未初始化时返回None，获取连接失败时返回错误
+pub async fn try_get_connection(pool: &RedisPool) -> Option<Result<Connection, String>> {
+    let pool = pool.as_ref().as_ref()?;
+    Some(pool.get().await.map_err(|e| format!("获取Redis连接失败: {}", e)))
+}
+
+// 获取Redis连接，连接池未初始化或获取连接失败时返回None，由调用方退回进程内存储
+pub async fn get_connection_or_fallback(pool: &RedisPool, component: &str) -> Option<Connection> {
+    match try_get_connection(pool).await? {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            error!("{}，{}退回进程内存储", e, component);
+            None
+        },
+    }
+}
+
 // 初始化Redis连接池
-pub fn init_redis_pool() -> Result<RedisPool, Box<dyn std::error::Error>> {
+pub fn init_redis_pool(redis_config: &RedisConfig) -> Result<RedisPool, Box<dyn std::error::Error>> {
     // 创建Redis连接配置
-    let mut config = Config::default();
-    // 设置Redis服务器地址，默认端口6379,库可以自己选择
-    config.url = Some("redis://localhost:6379/1".to_string());
-    // 可以根据需要配置其他选项，如超时、密码等
+    let mut config = Config::from_url(redis_config.url.clone());
+    // 设置连接池大小
+    if let Some(max_size) = redis_config.max_connections {
+        config.pool = Some(PoolConfig::new(max_size));
+    }
     
     // 构建连接池
     let pool = config.create_pool(Some(Runtime::Tokio1))?;
@@ -55,6 +75,38 @@ pub async fn set_with_expiry(
     Ok(())
 }
 
+// 设置不过期的键值对
+pub async fn set(
+    conn: &mut Connection,
+    key: &str,
+    value: &str
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _: () = cmd("SET")
+        .arg(key)
+        .arg(value)
+        .query_async(conn)
+        .await?;
+    Ok(())
+}
+
+// 仅在键不存在时设置带过期时间的键值对，返回是否设置成功
+pub async fn set_nx_with_expiry(
+    conn: &mut Connection,
+    key: &str,
+    value: &str,
+    expiry_seconds: u64
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let result: Option<String> = cmd("SET")
+        .arg(key)
+        .arg(value)
+        .arg("NX")
+        .arg("EX")
+        .arg(expiry_seconds)
+        .query_async(conn)
+        .await?;
+    Ok(result.is_some())
+}
+
 // 获取键值
 pub async fn get(
     conn: &mut Connection,
@@ -80,6 +132,7 @@ pub async fn del(
 }
 
 // 增加计数器
+#[allow(dead_code)]
 pub async fn incr(
     conn: &mut Connection,
     key: &str
@@ -91,7 +144,81 @@ pub async fn incr(
     Ok(result)
 }
 
+// incr_with_expiry 使用的Lua脚本，计数器是新建的或没有过期时间时设置过期时间
+const INCR_WITH_EXPIRY_SCRIPT: &str = "\
+local count = redis.call('INCR', KEYS[1])
+if count == 1 or redis.call('TTL', KEYS[1]) < 0 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return count";
+
+// 计数器加一，第一次递增时设置过期时间，之后的递增保留原有的过期时间
+// INCR 和 EXPIRE 在同一个Lua脚本中原子执行，计数器不会因进程或连接中断而永不过期
+pub async fn incr_with_expiry(
+    conn: &mut Connection,
+    key: &str,
+    expiry_seconds: u64
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let result: i64 = cmd("EVAL")
+        .arg(INCR_WITH_EXPIRY_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(expiry_seconds)
+        .query_async(conn)
+        .await?;
+    Ok(result)
+}
+
+// decr_if_exists 使用的Lua脚本
+const DECR_IF_EXISTS_SCRIPT: &str = "\
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    return redis.call('DECR', KEYS[1])
+end
+return 0";
+
+// 键存在时计数器减一并返回新值，键不存在时返回0且不创建键
+pub async fn decr_if_exists(
+    conn: &mut Connection,
+    key: &str
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let result: i64 = cmd("EVAL")
+        .arg(DECR_IF_EXISTS_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .query_async(conn)
+        .await?;
+    Ok(result)
+}
+
+// 设置键的过期时间
+#[allow(dead_code)]
+pub async fn expire(
+    conn: &mut Connection,
+    key: &str,
+    expiry_seconds: u64
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _: () = cmd("EXPIRE")
+        .arg(key)
+        .arg(expiry_seconds)
+        .query_async(conn)
+        .await?;
+    Ok(())
+}
+
+// 获取键的剩余过期时间（秒），键不存在时返回-2，未设置过期时间时返回-1
+pub async fn ttl(
+    conn: &mut Connection,
+    key: &str
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let result: i64 = cmd("TTL")
+        .arg(key)
+        .query_async(conn)
+        .await?;
+    Ok(result)
+}
+
 // 存储哈希值
+#[allow(dead_code)]
 pub async fn hset(
     conn: &mut Connection,
     key: &str,
@@ -108,6 +235,7 @@ pub async fn hset(
 }
 
 // 获取哈希值
+#[allow(dead_code)]
 pub async fn hget(
     conn: &mut Connection,
     key: &str,
@@ -0,0 +1,341 @@
+use actix_web::{web, HttpResponse, Error};
+use serde::Deserialize;
+use serde_json::json;
+use log::{info, error};
+use crate::api_keys;
+use crate::db::{DbPool, UserGrants, get_connection_or_return_error, load_user_grants, user_exists};
+use crate::middleware::jwt::AuthenticatedUser;
+use crate::utils::time::unix_now;
+
+// API密钥的最长有效期（秒），保证过期时间在 FROM_UNIXTIME 的取值范围内
+// 超出范围时 FROM_UNIXTIME 返回 NULL，密钥会变成永不过期
+const MAX_EXPIRES_IN_SECS: u64 = 10 * 365 * 24 * 3600;
+
+// 创建API密钥请求结构
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    name: String,
+    owner_id: Option<u64>,       // 密钥所属用户，默认为当前管理员
+    scopes: Vec<String>,         // 授予的权限，例如 ["cache:read", "cache:write"]
+    expires_in: Option<u64>,     // 可选的有效期（秒），不设置则永不过期，最长10年
+}
+
+// 创建API密钥，明文密钥只在响应中返回一次
+pub async fn create_api_key(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    request: web::Json<CreateApiKeyRequest>,
+) -> Result<HttpResponse, Error> {
+    let request = request.into_inner();
+    if request.name.trim().is_empty() || request.name.len() > 100 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "name 不能为空且长度不能超过100"
+        })));
+    }
+    if request.scopes.is_empty() || request.scopes.iter().any(|scope| scope.is_empty() || scope.contains(',') || scope.contains(char::is_whitespace)) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "scopes 不能为空，且每个权限不能包含逗号或空白字符"
+        })));
+    }
+
+    let expires_at = match request.expires_in {
+        Some(expires_in) => match expires_at(unix_now(), expires_in) {
+            Some(expires_at) => Some(expires_at),
+            None => return Ok(HttpResponse::BadRequest().json(json!({
+                "status": "error",
+                "message": format!("expires_in 必须在 1 到 {} 秒之间", MAX_EXPIRES_IN_SECS)
+            }))),
+        },
+        None => None,
+    };
+
+    // 密钥以所属用户的身份执行，所属用户必须存在且拥有全部申请的权限
+    let owner_id = request.owner_id.unwrap_or(user.id);
+    let mut conn = get_connection_or_return_error(&pool).await?;
+    let owner_grants = match user_exists(&mut conn, owner_id) {
+        Ok(true) => load_user_grants(&mut conn, owner_id),
+        Ok(false) => return Ok(HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("用户 {} 不存在", owner_id)
+        }))),
+        Err(e) => Err(e),
+    };
+    let owner_grants = match owner_grants {
+        Ok(grants) => grants,
+        Err(e) => {
+            error!("加载密钥所属用户的权限失败: {}, owner_id={}", e, owner_id);
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "创建API密钥失败"
+            })));
+        },
+    };
+    let missing = missing_scopes(&owner_grants, &request.scopes);
+    if !missing.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("用户 {} 没有以下权限: {}", owner_id, missing.join(","))
+        })));
+    }
+
+    match api_keys::create_api_key(&mut conn, request.name.trim(), owner_id, &request.scopes, expires_at) {
+        Ok(new_key) => {
+            info!("API密钥已创建: id={}, owner_id={}, 操作人={}", new_key.api_key.id, owner_id, user.id);
+            Ok(HttpResponse::Created().json(json!({
+                "status": "success",
+                "key": new_key.key,
+                "api_key": new_key.api_key
+            })))
+        },
+        Err(e) => {
+            error!("创建API密钥失败: {}", e);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "创建API密钥失败"
+            })))
+        },
+    }
+}
+
+// 计算过期时间，有效期为0、超过上限或溢出时返回None
+fn expires_at(now: u64, expires_in: u64) -> Option<u64> {
+    if expires_in == 0 || expires_in > MAX_EXPIRES_IN_SECS {
+        return None;
+    }
+    now.checked_add(expires_in)
+}
+
+// 所属用户没有的权限，'*' 只有拥有全部权限的用户才能授予
+fn missing_scopes<'a>(grants: &UserGrants, scopes: &'a [String]) -> Vec<&'a str> {
+    scopes.iter().filter(|scope| !grants.has_permission(scope)).map(String::as_str).collect()
+}
+
+// 列出所有API密钥
+pub async fn list_api_keys(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+    let mut conn = get_connection_or_return_error(&pool).await?;
+    match api_keys::list_api_keys(&mut conn) {
+        Ok(keys) => Ok(HttpResponse::Ok().json(json!({
+            "status": "success",
+            "api_keys": keys
+        }))),
+        Err(e) => {
+            error!("查询API密钥失败: {}", e);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "查询API密钥失败"
+            })))
+        },
+    }
+}
+
+// 吊销API密钥
+pub async fn revoke_api_key(
+    user: AuthenticatedUser,
+    pool: web::Data<DbPool>,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    let mut conn = get_connection_or_return_error(&pool).await?;
+    match api_keys::revoke_api_key(&mut conn, id) {
+        Ok(true) => {
+            info!("API密钥已吊销: id={}, 操作人={}", id, user.id);
+            Ok(HttpResponse::Ok().json(json!({
+                "status": "success",
+                "message": format!("API密钥 {} 已吊销", id)
+            })))
+        },
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("API密钥 {} 不存在或已吊销", id)
+        }))),
+        Err(e) => {
+            error!("吊销API密钥失败: {}, id={}", e, id);
+            Ok(HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": "吊销API密钥失败"
+            })))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use actix_web::App;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use mysql::prelude::Queryable;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use crate::middleware::JwtMiddleware;
+    use crate::middleware::authorization::Authorize;
+    use crate::middleware::jwt_keys::JwtKeySet;
+    use crate::routes::test_support;
+    use crate::token_store::TokenStore;
+    use super::*;
+
+    fn grants(permissions: &[&str]) -> UserGrants {
+        UserGrants {
+            roles: Vec::new(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn expires_at_rejects_zero_overlong_and_overflowing_lifetimes() {
+        assert_eq!(expires_at(1_000, 60), Some(1_060));
+        assert_eq!(expires_at(1_000, MAX_EXPIRES_IN_SECS), Some(1_000 + MAX_EXPIRES_IN_SECS));
+        assert_eq!(expires_at(1_000, 0), None);
+        assert_eq!(expires_at(1_000, MAX_EXPIRES_IN_SECS + 1), None);
+        assert_eq!(expires_at(1_000, u64::MAX), None);
+        assert_eq!(expires_at(u64::MAX - 10, 60), None);
+    }
+
+    #[test]
+    fn missing_scopes_only_allows_permissions_the_owner_holds() {
+        let scopes = vec!["cache:read".to_string(), "cache:write".to_string(), "*".to_string()];
+        assert_eq!(missing_scopes(&grants(&["cache:read", "users:read"]), &scopes), vec!["cache:write", "*"]);
+        assert!(missing_scopes(&grants(&["*"]), &scopes).is_empty());
+        assert_eq!(missing_scopes(&grants(&[]), &scopes[..1]), vec!["cache:read"]);
+    }
+
+    // 以下测试需要测试数据库，见 test_support
+
+    fn test_jwt_middleware() -> JwtMiddleware {
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        JwtMiddleware::new(JwtKeySet::load(&test_support::app_config().jwt).unwrap()).with_api_key_cache(cache)
+    }
+
+    // 与 main_routes 相同的 /admin/api-keys 路由，另加一个需要 cache:read 权限的探测接口，返回当前身份
+    fn routes(cfg: &mut web::ServiceConfig) {
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        cfg.app_data(web::Data::new(test_support::db_pool()))
+            .app_data(web::Data::new(TokenStore::new(Arc::new(None), cache, Duration::from_secs(3600))))
+            .service(
+                web::scope("/admin")
+                    .wrap(Authorize::role("admin"))
+                    .route("/api-keys", web::post().to(create_api_key))
+                    .route("/api-keys", web::get().to(list_api_keys))
+                    .route("/api-keys/{id}", web::delete().to(revoke_api_key))
+            )
+            .route("/probe", web::get().to(|user: AuthenticatedUser| async move {
+                HttpResponse::Ok().json(json!({ "user_id": user.id }))
+            }).wrap(Authorize::permission("cache:read")));
+    }
+
+    // 中间件返回的错误也转换为响应状态码
+    async fn send<S, R, B>(app: &S, req: R) -> (StatusCode, serde_json::Value)
+    where
+        S: Service<R, Response = ServiceResponse<B>, Error = Error>,
+        B: MessageBody,
+    {
+        match app.call(req).await {
+            Ok(resp) => {
+                let status = resp.status();
+                let body = actix_web::test::read_body(resp).await;
+                (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+            },
+            Err(e) => (e.as_response_error().status_code(), serde_json::Value::Null),
+        }
+    }
+
+    fn admin_token(jwt_middleware: &JwtMiddleware, admin_id: u64, phone: &str) -> String {
+        let grants = UserGrants { roles: vec!["admin".to_string()], permissions: vec!["*".to_string()] };
+        let token = jwt_middleware.generate_token(admin_id, phone.to_string(), &grants, Duration::from_secs(600)).unwrap();
+        format!("Bearer {}", token)
+    }
+
+    fn delete_api_keys(owner_id: u64) {
+        let mut conn = test_support::db_pool().get().unwrap();
+        conn.exec_drop("DELETE FROM api_keys WHERE owner_id = ?", (owner_id,)).unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn api_key_lifecycle_through_the_middleware() {
+        let pool = test_support::db_pool();
+        let (admin_phone, owner_phone) = (test_support::unique_phone("1391"), test_support::unique_phone("1392"));
+        let admin_id = test_support::create_user(&pool, &admin_phone, "admin");
+        let owner_id = test_support::create_user(&pool, &owner_phone, "user");
+        let jwt_middleware = test_jwt_middleware();
+        let app = actix_web::test::init_service(App::new().wrap(jwt_middleware.clone()).configure(routes)).await;
+        let admin = admin_token(&jwt_middleware, admin_id, &admin_phone);
+
+        let (status, body) = send(&app, actix_web::test::TestRequest::post().uri("/admin/api-keys")
+            .insert_header(("Authorization", admin.as_str()))
+            .set_json(json!({ "name": "lifecycle", "owner_id": owner_id, "scopes": ["cache:read"], "expires_in": 3600 }))
+            .to_request()).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let key = body["key"].as_str().unwrap().to_string();
+        let id = body["api_key"]["id"].as_u64().unwrap();
+        assert_eq!(body["api_key"]["owner_id"], json!(owner_id));
+
+        // 密钥以所属用户的身份通过认证和权限检查
+        let probe = || actix_web::test::TestRequest::get().uri("/probe").insert_header(("X-API-Key", key.as_str())).to_request();
+        let (status, body) = send(&app, probe()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user_id"], json!(owner_id));
+
+        // 密钥不能访问超出其权限的接口
+        let (status, _) = send(&app, actix_web::test::TestRequest::get().uri("/admin/api-keys")
+            .insert_header(("X-API-Key", key.as_str())).to_request()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // 列表中包含新密钥，但不返回明文密钥
+        let (status, body) = send(&app, actix_web::test::TestRequest::get().uri("/admin/api-keys")
+            .insert_header(("Authorization", admin.as_str())).to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        let listed = body["api_keys"].as_array().unwrap().iter().find(|k| k["id"] == json!(id)).cloned().unwrap();
+        assert_eq!(listed["scopes"], json!(["cache:read"]));
+        assert!(listed["revoked_at"].is_null());
+        assert!(!body.to_string().contains(&key));
+
+        // 吊销后即使密钥ID已被缓存也无法再使用，重复吊销返回404
+        let revoke = || actix_web::test::TestRequest::delete().uri(&format!("/admin/api-keys/{}", id))
+            .insert_header(("Authorization", admin.as_str())).to_request();
+        assert_eq!(send(&app, revoke()).await.0, StatusCode::OK);
+        assert_eq!(send(&app, probe()).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(&app, revoke()).await.0, StatusCode::NOT_FOUND);
+
+        delete_api_keys(owner_id);
+        test_support::delete_user(&pool, &admin_phone);
+        test_support::delete_user(&pool, &owner_phone);
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn create_rejects_unknown_owners_unheld_scopes_and_bad_expiry() {
+        let pool = test_support::db_pool();
+        let (admin_phone, owner_phone) = (test_support::unique_phone("1393"), test_support::unique_phone("1394"));
+        let admin_id = test_support::create_user(&pool, &admin_phone, "admin");
+        let owner_id = test_support::create_user(&pool, &owner_phone, "user");
+        let jwt_middleware = test_jwt_middleware();
+        let app = actix_web::test::init_service(App::new().wrap(jwt_middleware.clone()).configure(routes)).await;
+        let admin = admin_token(&jwt_middleware, admin_id, &admin_phone);
+
+        for request in [
+            json!({ "name": "unknown owner", "owner_id": u64::MAX, "scopes": ["cache:read"] }),
+            json!({ "name": "wildcard", "owner_id": owner_id, "scopes": ["*"] }),
+            json!({ "name": "unheld scope", "owner_id": owner_id, "scopes": ["cache:read", "cache:write"] }),
+            json!({ "name": "zero expiry", "owner_id": owner_id, "scopes": ["cache:read"], "expires_in": 0 }),
+            json!({ "name": "overlong expiry", "owner_id": owner_id, "scopes": ["cache:read"], "expires_in": MAX_EXPIRES_IN_SECS + 1 }),
+            json!({ "name": "overflowing expiry", "owner_id": owner_id, "scopes": ["cache:read"], "expires_in": u64::MAX }),
+        ] {
+            let (status, body) = send(&app, actix_web::test::TestRequest::post().uri("/admin/api-keys")
+                .insert_header(("Authorization", admin.as_str()))
+                .set_json(&request)
+                .to_request()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{} -> {}", request, body);
+        }
+
+        let mut conn = pool.get().unwrap();
+        let created: Option<u64> = conn.exec_first("SELECT COUNT(*) FROM api_keys WHERE owner_id IN (?, ?)", (owner_id, u64::MAX)).unwrap();
+        assert_eq!(created, Some(0));
+
+        test_support::delete_user(&pool, &admin_phone);
+        test_support::delete_user(&pool, &owner_phone);
+    }
+}
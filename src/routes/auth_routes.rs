@@ -1,16 +1,27 @@
 use mysql::prelude::Queryable;
-use actix_web::{web, HttpResponse, Responder, HttpRequest, HttpMessage, Error}; 
+use actix_web::{web, HttpRequest, HttpResponse, Responder, Error}; 
 use serde::{Deserialize, Serialize}; 
 use std::time::Duration; 
 use log::{info, error}; 
 use crate::middleware::JwtMiddleware; 
-use crate::db::{DbPool, get_connection_or_return_error}; 
+use crate::middleware::jwt::AuthenticatedUser;
+use crate::db::{DbPool, UserGrants, DEFAULT_ROLE, get_connection_or_return_error, grant_role, load_user_grants}; 
+use crate::password::PasswordService;
+use crate::token_store::{IssuedRefreshToken, TokenStore, TokenStoreError};
+use crate::login_throttle::{LoginCheck, LoginThrottle};
+use crate::config;
+
+// 认证相关SQL，所有请求字段都通过占位符绑定，禁止拼接到SQL字符串中
+const SQL_FIND_USER_BY_PHONE: &str = "SELECT id, phone, password FROM users WHERE phone = ? LIMIT 1";
+const SQL_USER_EXISTS: &str = "SELECT id FROM users WHERE phone = ? LIMIT 1";
+const SQL_INSERT_USER: &str = "INSERT INTO users (phone, password, name) VALUES (?, ?, ?)";
+const SQL_UPDATE_PASSWORD: &str = "UPDATE users SET password = ? WHERE id = ?";
 
 // 登录请求结构体
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub phone: String, 
-    pub password: String, // 在实际应用中应该使用加密密码
+    pub password: String, 
 }
 
 // 登录响应结构体
@@ -19,10 +30,24 @@ pub struct LoginResponse {
     pub token: String, 
     pub token_type: String, 
     pub expires_in: u64, 
+    pub refresh_token: String, 
+    pub refresh_expires_in: u64, 
     pub user_id: u64, 
     pub phone: String, 
 }
 
+// 刷新令牌请求结构体
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String, 
+}
+
+// 登出请求结构体，可同时吊销刷新令牌
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>, 
+}
+
 // 注册请求结构体
 #[derive(Debug, Deserialize)]
 pub struct RegisterRequest {
@@ -41,85 +66,275 @@ pub struct RegisterResponse {
 
 // 登录处理函数
 pub async fn login(
+    http_req: HttpRequest,
     req: web::Json<LoginRequest>, 
     pool: web::Data<DbPool>, 
     jwt_middleware: web::Data<JwtMiddleware>,
+    password_service: web::Data<PasswordService>,
+    token_store: web::Data<TokenStore>,
+    login_throttle: web::Data<LoginThrottle>,
 ) -> Result<HttpResponse, Error> {
+    // 失败次数过多的手机号或IP直接拒绝，不再校验密码
+    let client_ip = http_req.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+    // 校验密码前先计入本次尝试，并发的猜测同样受次数限制
+    let attempt = match login_throttle.begin_attempt(&req.phone, &client_ip).await {
+        LoginCheck::Allowed(attempt) => attempt,
+        LoginCheck::Locked { retry_after } => {
+            error!("登录已被临时锁定: phone={}, ip={}, retry_after={}s", req.phone, client_ip, retry_after);
+            return Ok(HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .json(serde_json::json!({
+                    "error": "Too many failed login attempts, try again later",
+                    "retry_after": retry_after
+                })));
+        },
+    };
+    
     let mut conn = get_connection_or_return_error(&pool).await?;
     
-    // 查找用户 - 在实际应用中应该使用参数化查询防止SQL注入
-    let query = format!("SELECT id, phone FROM users WHERE phone = '{}' AND password = '{}' LIMIT 1", req.phone, req.password);
+    // 按手机号查找用户，密码在应用内校验
+    let user = match conn.exec_first::<(u64, String, String), _, _>(SQL_FIND_USER_BY_PHONE, (&req.phone,)) {
+        Ok(user) => user,
+        Err(e) => {
+            error!("数据库查询失败: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Database error"})));
+        },
+    };
+    
+    // argon2 计算开销较大，放到阻塞线程池执行
+    // 用户不存在时同样校验一次占位哈希，避免通过响应时间判断手机号是否已注册
+    let service = password_service.clone();
+    let password = req.password.clone();
+    let verified = match user {
+        Some((user_id, phone, stored_password)) => {
+            let verification = web::block(move || service.verify(&password, &stored_password)).await?;
+            verification.valid.then_some((user_id, phone, verification.needs_rehash))
+        },
+        None => {
+            web::block(move || service.verify_unknown_user(&password)).await?;
+            None
+        },
+    };
     
-    match conn.query_first::<(u64, String), &str>(&query) {
-        Ok(Some((user_id, phone))) => {
+    match verified {
+        Some((user_id, phone, needs_rehash)) => {
             info!("用户登录成功: phone={}, user_id={}", phone, user_id);
+            login_throttle.record_success(&phone, &client_ip).await;
+            
+            // 旧的明文密码或哈希参数已变更时，重新哈希并写回数据库
+            if needs_rehash {
+                let service = password_service.clone();
+                let password = req.password.clone();
+                match web::block(move || service.hash(&password)).await? {
+                    Ok(new_hash) => {
+                        match conn.exec_drop(SQL_UPDATE_PASSWORD, (new_hash, user_id)) {
+                            Ok(_) => info!("用户密码哈希已升级: user_id={}", user_id),
+                            Err(e) => error!("更新密码哈希失败: {}, user_id={}", e, user_id),
+                        }
+                    },
+                    Err(e) => error!("重新哈希密码失败: {}, user_id={}", e, user_id),
+                }
+            }
             
-            // 生成JWT令牌，有效期为7天
-            match jwt_middleware.generate_token(user_id, phone.clone(), Duration::from_secs(7 * 24 * 60 * 60)) {
-                Ok(token) => {
-                    Ok(HttpResponse::Ok().json(LoginResponse {
-                        token,
-                        token_type: "Bearer".to_string(),
-                        expires_in: 7 * 24 * 60 * 60, 
-                        user_id,
-                        phone,
-                    }))
+            // 加载用户角色与权限，写入访问令牌
+            let grants = match load_user_grants(&mut conn, user_id) {
+                Ok(grants) => grants,
+                Err(e) => {
+                    error!("加载用户权限失败: {}, user_id={}", e, user_id);
+                    return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Database error"})));
                 },
+            };
+            
+            // 签发刷新令牌，开启新的令牌族
+            match token_store.issue_refresh_token(user_id, phone).await {
+                Ok(refresh) => Ok(token_response(&jwt_middleware, refresh, &grants)),
                 Err(e) => {
-                    error!("生成JWT令牌失败: {}", e);
+                    error!("签发刷新令牌失败: {}", e);
                     Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to generate token"})))
                 },
             }
         },
-        Ok(None) => {
-            error!("登录失败: 用户名或密码错误，phone={}", req.phone);
+        None => {
+            error!("登录失败: 用户名或密码错误，phone={}, ip={}", req.phone, client_ip);
+            // 逐步增加失败响应的延迟，拖慢暴力破解
+            let delay = login_throttle.record_failure(&req.phone, &client_ip, attempt).await;
+            if !delay.is_zero() {
+                actix_web::rt::time::sleep(delay).await;
+            }
             Ok(HttpResponse::Unauthorized().json(serde_json::json!({"error": "Invalid phone or password"})))
         },
+    }
+}
+
+// 刷新令牌处理函数：轮换刷新令牌并签发新的访问令牌
+pub async fn refresh(
+    req: web::Json<RefreshRequest>, 
+    pool: web::Data<DbPool>, 
+    jwt_middleware: web::Data<JwtMiddleware>,
+    token_store: web::Data<TokenStore>,
+) -> Result<HttpResponse, Error> {
+    match token_store.rotate_refresh_token(&req.refresh_token).await {
+        Ok(refresh) => {
+            info!("刷新令牌轮换成功: user_id={}", refresh.record.user_id);
+            
+            // 重新加载角色与权限，使权限变更在刷新后生效
+            let mut conn = get_connection_or_return_error(&pool).await?;
+            match load_user_grants(&mut conn, refresh.record.user_id) {
+                Ok(grants) => Ok(token_response(&jwt_middleware, refresh, &grants)),
+                Err(e) => {
+                    error!("加载用户权限失败: {}, user_id={}", e, refresh.record.user_id);
+                    Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Database error"})))
+                },
+            }
+        },
+        Err(TokenStoreError::Backend(e)) => {
+            // 存储不可用时无法确认令牌状态，拒绝轮换
+            error!("刷新令牌失败: {}", e);
+            Ok(HttpResponse::ServiceUnavailable().json(serde_json::json!({"error": "Failed to refresh token"})))
+        },
         Err(e) => {
-            error!("数据库查询失败: {}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Database error"})))
+            error!("刷新令牌被拒绝: {}", e);
+            Ok(HttpResponse::Unauthorized().json(serde_json::json!({"error": e.to_string()})))
         },
     }
 }
 
+// 登出处理函数：吊销当前访问令牌，若提供刷新令牌则一并吊销其令牌族
+pub async fn logout(
+    user: AuthenticatedUser,
+    body: Option<web::Json<LogoutRequest>>,
+    token_store: web::Data<TokenStore>,
+) -> Result<HttpResponse, Error> {
+    if user.api_key_id.is_some() {
+        return Ok(api_key_logout_response());
+    }
+    
+    if let Err(e) = token_store.revoke_access_token(&user.token_id, user.expires_at).await {
+        error!("吊销访问令牌失败: {}, user_id={}", e, user.id);
+        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to logout"})));
+    }
+    
+    if let Some(refresh_token) = body.and_then(|body| body.into_inner().refresh_token)
+        && let Err(e) = token_store.revoke_refresh_token(&refresh_token).await {
+        error!("吊销刷新令牌失败: {}, user_id={}", e, user.id);
+        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to logout"})));
+    }
+    
+    info!("用户登出: user_id={}", user.id);
+    Ok(HttpResponse::Ok().json(serde_json::json!({"success": true})))
+}
+
+// 登出所有会话：吊销该用户此前签发的所有访问令牌和刷新令牌
+pub async fn logout_all(
+    user: AuthenticatedUser,
+    token_store: web::Data<TokenStore>,
+) -> Result<HttpResponse, Error> {
+    if user.api_key_id.is_some() {
+        return Ok(api_key_logout_response());
+    }
+    
+    let user_id = user.id;
+    
+    match token_store.revoke_all_sessions(user_id).await {
+        Ok(_) => {
+            info!("用户登出所有会话: user_id={}", user_id);
+            Ok(HttpResponse::Ok().json(serde_json::json!({"success": true})))
+        },
+        Err(e) => {
+            error!("登出所有会话失败: {}, user_id={}", e, user_id);
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to logout"})))
+        },
+    }
+}
+
+// API密钥没有会话，需要通过管理接口吊销
+fn api_key_logout_response() -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({"error": "API keys cannot log out, revoke the key instead"}))
+}
+
+// 根据刷新令牌签发访问令牌并构造响应
+fn token_response(jwt_middleware: &JwtMiddleware, refresh: IssuedRefreshToken, grants: &UserGrants) -> HttpResponse {
+    let jwt_config = &config::get().jwt;
+    let IssuedRefreshToken { token: refresh_token, record } = refresh;
+    
+    match jwt_middleware.generate_token(record.user_id, record.subject.clone(), grants, Duration::from_secs(jwt_config.access_token_ttl_secs)) {
+        Ok(token) => HttpResponse::Ok().json(LoginResponse {
+            token,
+            token_type: "Bearer".to_string(),
+            expires_in: jwt_config.access_token_ttl_secs, 
+            refresh_token,
+            refresh_expires_in: jwt_config.refresh_token_ttl_secs, 
+            user_id: record.user_id,
+            phone: record.subject,
+        }),
+        Err(e) => {
+            error!("生成JWT令牌失败: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to generate token"}))
+        },
+    }
+}
+
+// 手机号只允许数字和可选的前导 '+'，长度 5-20
+fn is_valid_phone(phone: &str) -> bool {
+    let digits = phone.strip_prefix('+').unwrap_or(phone);
+    (5..=20).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
 // 注册处理函数
 pub async fn register(
     req: web::Json<RegisterRequest>, 
     pool: web::Data<DbPool>,
+    password_service: web::Data<PasswordService>,
 ) -> Result<HttpResponse, Error> {
+    if !is_valid_phone(&req.phone) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({"error": "Invalid phone number"})));
+    }
+    
     let mut conn = get_connection_or_return_error(&pool).await?;
     
     // 检查用户是否已存在
-    let check_query = format!("SELECT id FROM users WHERE phone = '{}' LIMIT 1", req.phone);
-    
-    if let Ok(Some((_id,))) = conn.query_first::<(u64,), &str>(&check_query) {
-        return Ok(HttpResponse::Conflict().json(serde_json::json!({"error": "User with this phone already exists"})));
+    match conn.exec_first::<(u64,), _, _>(SQL_USER_EXISTS, (&req.phone,)) {
+        Ok(Some(_)) => {
+            return Ok(HttpResponse::Conflict().json(serde_json::json!({"error": "User with this phone already exists"})));
+        },
+        Ok(None) => {},
+        Err(e) => {
+            error!("数据库查询失败: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Database error"})));
+        },
     }
     
+    // 对密码进行哈希
+    let service = password_service.clone();
+    let password = req.password.clone();
+    let password_hash = match web::block(move || service.hash(&password)).await? {
+        Ok(password_hash) => password_hash,
+        Err(e) => {
+            error!("密码哈希失败: {}, phone={}", e, req.phone);
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({"error": "Failed to register user"})));
+        },
+    };
+    
     // 创建新用户
     let empty_string = String::from("");
     let name = req.name.as_ref().unwrap_or(&empty_string);
-    let insert_query = format!(
-        "INSERT INTO users (phone, password, name) VALUES ('{}', '{}', '{}')",
-        req.phone, req.password, name
-    );
     
-    match conn.query_drop(&insert_query) {
+    match conn.exec_drop(SQL_INSERT_USER, (&req.phone, &password_hash, name)) {
         Ok(_) => {
             // 获取新创建的用户ID
-            if let Ok(Some((user_id,))) = conn.query_first::<(u64,), &str>("SELECT LAST_INSERT_ID()") {
+            let user_id = conn.last_insert_id();
+            if user_id == 0 {
+                error!("注册成功但无法获取用户ID: phone={}", req.phone);
+            } else {
                 info!("用户注册成功: phone={}, user_id={}", req.phone, user_id);
-                return Ok(HttpResponse::Ok().json(RegisterResponse {
-                    success: true,
-                    user_id,
-                    phone: req.phone.clone(),
-                }));
+                // 授予默认角色
+                if let Err(e) = grant_role(&mut conn, user_id, DEFAULT_ROLE) {
+                    error!("授予默认角色失败: {}, user_id={}", e, user_id);
+                }
             }
-            
-            error!("注册成功但无法获取用户ID: phone={}", req.phone);
             Ok(HttpResponse::Ok().json(RegisterResponse {
                 success: true,
-                user_id: 0, // 无法获取ID的情况
+                user_id,
                 phone: req.phone.clone(),
             }))
         },
@@ -131,18 +346,238 @@ pub async fn register(
 }
 
 // 获取当前用户信息 - 受JWT保护的路由示例
-pub async fn get_current_user(req: HttpRequest) -> impl Responder {
-    // 从请求扩展中获取用户ID和用户名
-    if let (Some(user_id), Some(username)) = (
-        req.extensions().get::<u64>(),
-        req.extensions().get::<String>()
-    ) {
-        HttpResponse::Ok().json(serde_json::json!({
-            "user_id": user_id,
-            "username": username,
-            "message": "This is protected data"
-        }))
-    } else {
-        HttpResponse::Unauthorized().json(serde_json::json!({"error": "User not authenticated"}))
-    }
-}
\ No newline at end of file
+pub async fn get_current_user(user: AuthenticatedUser) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "user_id": user.id,
+        "username": user.subject,
+        "roles": user.roles,
+        "permissions": user.permissions,
+        "message": "This is protected data"
+    }))
+}
+// 公开的 JWK Set，供其他服务验证本服务签发的令牌
+pub async fn jwks(jwt_middleware: web::Data<JwtMiddleware>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(jwt_middleware.jwks())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, OnceLock};
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use crate::config::{DatabaseConfig, JwtConfig, LoginThrottleConfig, PasswordConfig};
+    use crate::db::init_db_pool;
+    use crate::middleware::jwt_keys::JwtKeySet;
+    use crate::routes::test_support;
+    use super::*;
+
+    // 标记为 ignore 的测试需要测试数据库，见 test_support
+    struct TestData {
+        pool: web::Data<DbPool>,
+        jwt_middleware: web::Data<JwtMiddleware>,
+        password_service: web::Data<PasswordService>,
+        token_store: web::Data<TokenStore>,
+        login_throttle: web::Data<LoginThrottle>,
+    }
+
+    fn test_data() -> &'static TestData {
+        static DATA: OnceLock<TestData> = OnceLock::new();
+        DATA.get_or_init(|| {
+            let app_config = test_support::app_config();
+            let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+            TestData {
+                pool: web::Data::new(test_support::db_pool()),
+                jwt_middleware: web::Data::new(JwtMiddleware::new(JwtKeySet::load(&app_config.jwt).unwrap())),
+                password_service: web::Data::new(PasswordService::new(&app_config.password).unwrap()),
+                token_store: web::Data::new(TokenStore::new(Arc::new(None), cache.clone(), Duration::from_secs(3600))),
+                // 测试会连续使用错误密码登录，关闭登录限制以免被锁定
+                login_throttle: web::Data::new(LoginThrottle::new(Arc::new(None), cache, LoginThrottleConfig { enabled: false, ..Default::default() })),
+            }
+        })
+    }
+
+    // 指向不可达数据库的依赖，用于验证请求在访问数据库之前就被拒绝：一旦访问数据库就会返回500
+    fn offline_data() -> TestData {
+        let database = DatabaseConfig { port: 1, connection_timeout_secs: 1, ..DatabaseConfig::default() };
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        let password = PasswordConfig { memory_kib: 64, iterations: 1, parallelism: 1 };
+        TestData {
+            pool: web::Data::new(init_db_pool(&database).unwrap()),
+            jwt_middleware: web::Data::new(JwtMiddleware::new(JwtKeySet::load(&JwtConfig::default()).unwrap())),
+            password_service: web::Data::new(PasswordService::new(&password).unwrap()),
+            token_store: web::Data::new(TokenStore::new(Arc::new(None), cache.clone(), Duration::from_secs(3600))),
+            login_throttle: web::Data::new(LoginThrottle::new(Arc::new(None), cache, Default::default())),
+        }
+    }
+
+    // 调用登录或注册接口，返回状态码和响应体
+    async fn post_json(data: &TestData, path: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(data.pool.clone())
+                .app_data(data.jwt_middleware.clone())
+                .app_data(data.password_service.clone())
+                .app_data(data.token_store.clone())
+                .app_data(data.login_throttle.clone())
+                .route("/auth/login", web::post().to(login))
+                .route("/auth/register", web::post().to(register)),
+        ).await;
+        let req = actix_web::test::TestRequest::post()
+            .uri(path)
+            .peer_addr("127.0.0.1:40000".parse().unwrap())
+            .set_json(body)
+            .to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        let status = resp.status();
+        let body = actix_web::test::read_body(resp).await;
+        (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+    }
+
+    fn stored_password(data: &TestData, phone: &str) -> Option<String> {
+        let mut conn = data.pool.get().unwrap();
+        conn.exec_first::<String, _, _>("SELECT password FROM users WHERE phone = ?", (phone,)).unwrap()
+    }
+
+    fn count_users(data: &TestData) -> u64 {
+        let mut conn = data.pool.get().unwrap();
+        conn.query_first::<u64, _>("SELECT COUNT(*) FROM users").unwrap().unwrap()
+    }
+
+    fn stored_name(data: &TestData, phone: &str) -> Option<String> {
+        let mut conn = data.pool.get().unwrap();
+        conn.exec_first::<String, _, _>("SELECT name FROM users WHERE phone = ?", (phone,)).unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn login_upgrades_legacy_plaintext_passwords() {
+        let data = test_data();
+        let phone = test_support::unique_phone("190");
+        data.pool.get().unwrap()
+            .exec_drop(SQL_INSERT_USER, (&phone, "legacy-secret", "legacy"))
+            .unwrap();
+
+        let (status, _) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": "legacy-secret"})).await;
+        assert_eq!(status, StatusCode::OK);
+        let upgraded = stored_password(data, &phone).unwrap();
+        assert!(upgraded.starts_with("$argon2id$"), "password was not rehashed: {}", upgraded);
+
+        // 升级后仍可用原密码登录，明文不再能作为密码使用
+        let (status, _) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": "legacy-secret"})).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": upgraded})).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        test_support::delete_user(&data.pool, &phone);
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn unknown_phone_and_wrong_password_get_the_same_response() {
+        let data = test_data();
+        let phone = test_support::unique_phone("191");
+        let (status, _) = post_json(data, "/auth/register", serde_json::json!({"phone": phone, "password": "secret"})).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (wrong_status, wrong_body) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": "wrong"})).await;
+        let (unknown_status, unknown_body) = post_json(data, "/auth/login", serde_json::json!({"phone": test_support::unique_phone("192"), "password": "wrong"})).await;
+        assert_eq!(wrong_status, StatusCode::UNAUTHORIZED);
+        assert_eq!((unknown_status, unknown_body), (wrong_status, wrong_body));
+        test_support::delete_user(&data.pool, &phone);
+    }
+
+    // 常见的SQL注入载荷，必须原样作为参数值传给数据库
+    const INJECTION_PAYLOADS: &[&str] = &[
+        "' OR '1'='1",
+        "' OR 1=1 -- ",
+        "13800000000' --",
+        "\"; DROP TABLE users; --",
+        "1; UPDATE users SET password = 'x' WHERE 1=1",
+        "' UNION SELECT id, phone, password FROM users --",
+        "\\'; SELECT SLEEP(5); #",
+        "13800000000\0' OR '1'='1",
+    ];
+
+    fn placeholders(sql: &str) -> usize {
+        sql.matches('?').count()
+    }
+
+    #[test]
+    fn queries_only_use_placeholders() {
+        for sql in [SQL_FIND_USER_BY_PHONE, SQL_USER_EXISTS, SQL_INSERT_USER, SQL_UPDATE_PASSWORD] {
+            assert!(!sql.contains('\''), "SQL must not embed literals: {}", sql);
+        }
+        assert_eq!(placeholders(SQL_FIND_USER_BY_PHONE), 1);
+        assert_eq!(placeholders(SQL_USER_EXISTS), 1);
+        assert_eq!(placeholders(SQL_INSERT_USER), 3);
+        assert_eq!(placeholders(SQL_UPDATE_PASSWORD), 2);
+    }
+
+    #[test]
+    fn injection_payloads_are_hashed_and_verified_as_plain_passwords() {
+        let service = PasswordService::new(&PasswordConfig { memory_kib: 64, iterations: 1, parallelism: 1 }).unwrap();
+        let stored = service.hash("correct horse battery staple").unwrap();
+        for payload in INJECTION_PAYLOADS {
+            assert!(!service.verify(payload, &stored).valid, "payload accepted: {:?}", payload);
+            assert!(!service.verify(payload, "legacy-plaintext").valid, "payload accepted: {:?}", payload);
+
+            let payload_hash = service.hash(payload).unwrap();
+            assert!(service.verify(payload, &payload_hash).valid);
+            assert!(!service.verify("correct horse battery staple", &payload_hash).valid);
+        }
+    }
+
+    #[actix_web::test]
+    async fn register_rejects_injection_phones_before_querying() {
+        let data = offline_data();
+        for payload in INJECTION_PAYLOADS {
+            let (status, _) = post_json(&data, "/auth/register", serde_json::json!({"phone": payload, "password": "secret"})).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "payload: {:?}", payload);
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn login_with_injection_payloads_authenticates_nobody() {
+        let data = test_data();
+        let phone = test_support::unique_phone("193");
+        let (status, _) = post_json(data, "/auth/register", serde_json::json!({"phone": phone, "password": "secret"})).await;
+        assert_eq!(status, StatusCode::OK);
+        let users = count_users(data);
+
+        for payload in INJECTION_PAYLOADS {
+            for body in [
+                serde_json::json!({"phone": payload, "password": payload}),
+                serde_json::json!({"phone": phone, "password": payload}),
+            ] {
+                let (status, body) = post_json(data, "/auth/login", body).await;
+                assert_eq!(status, StatusCode::UNAUTHORIZED, "payload: {:?}", payload);
+                assert!(body.get("token").is_none());
+            }
+        }
+        assert_eq!(count_users(data), users);
+        test_support::delete_user(&data.pool, &phone);
+    }
+
+    #[actix_web::test]
+    #[ignore = "需要测试数据库"]
+    async fn register_stores_injection_payloads_verbatim() {
+        let data = test_data();
+        for payload in INJECTION_PAYLOADS {
+            let phone = test_support::unique_phone("194");
+            let users = count_users(data);
+            let (status, _) = post_json(data, "/auth/register", serde_json::json!({"phone": phone, "password": payload, "name": payload})).await;
+            assert_eq!(status, StatusCode::OK, "payload: {:?}", payload);
+            // 只新增了一个用户，名称原样保存，密码按普通字符串哈希
+            assert_eq!(count_users(data), users + 1);
+            assert_eq!(stored_name(data, &phone).as_deref(), Some(*payload));
+            let (status, _) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": payload})).await;
+            assert_eq!(status, StatusCode::OK);
+            let (status, _) = post_json(data, "/auth/login", serde_json::json!({"phone": phone, "password": "' OR '1'='1"})).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            test_support::delete_user(&data.pool, &phone);
+        }
+    }
+}
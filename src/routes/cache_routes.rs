@@ -1,14 +1,79 @@
 use actix_web::{web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use crate::cache::Cache;
+use serde_json::{json, Value};
+use crate::cache::{Cache, CacheError, CacheStats, NamespaceConfig};
+use crate::utils::glob::glob_match;
+
+// 批量操作一次最多处理的键数量
+const MAX_BATCH_KEYS: usize = 1000;
+// 列出键时每页的默认数量和最大数量
+const DEFAULT_SCAN_LIMIT: usize = 100;
+const MAX_SCAN_LIMIT: usize = 1000;
 
 // 设置缓存请求结构
 #[derive(Debug, Deserialize)]
 pub struct SetCacheRequest {
     key: String,
-    value: String,
-    ttl: Option<u64>, // 可选的过期时间（秒）
+    value: Value, // 任意JSON值
+    ttl: Option<u64>, // 可选的过期时间（秒），不设置时使用命名空间的默认过期时间
+    #[serde(default)]
+    tags: Vec<String>, // 可选的标签，用于批量删除
+}
+
+// 批量读取或删除请求结构
+#[derive(Debug, Deserialize)]
+pub struct CacheKeysRequest {
+    keys: Vec<String>,
+}
+
+// 批量设置请求结构
+#[derive(Debug, Deserialize)]
+pub struct MsetCacheRequest {
+    entries: Vec<SetCacheRequest>,
+}
+
+// 列出键的查询参数
+#[derive(Debug, Deserialize)]
+pub struct ScanCacheQuery {
+    prefix: Option<String>,  // 键前缀
+    pattern: Option<String>, // 通配符，`*` 不匹配 ':'，`**` 匹配任意字符
+    cursor: Option<String>,  // 上一页返回的 next_cursor
+    limit: Option<usize>,
+}
+
+// 修改过期时间请求结构
+#[derive(Debug, Deserialize)]
+pub struct ExpireCacheRequest {
+    ttl: u64, // 新的过期时间（秒），从当前时间开始计算
+}
+
+// 计数器请求结构
+#[derive(Debug, Deserialize)]
+pub struct CounterRequest {
+    #[serde(default = "default_delta")]
+    delta: i64,       // 增减的数值，默认为1
+    ttl: Option<u64>, // 键不存在时新缓存项的过期时间（秒），已有的缓存项保留原有的过期时间
+}
+
+fn default_delta() -> i64 {
+    1
+}
+
+// 键不存在时设置缓存请求结构
+#[derive(Debug, Deserialize)]
+pub struct SetIfAbsentRequest {
+    key: String,
+    value: Value,
+    ttl: Option<u64>,
+}
+
+// 比较并替换请求结构
+#[derive(Debug, Deserialize)]
+pub struct CompareAndSwapRequest {
+    key: String,
+    expected: Value, // 期望的当前值，按JSON序列化后的字符串比较
+    value: Value,
+    ttl: Option<u64>, // 不设置时保留原有的过期时间
 }
 
 // 缓存状态响应结构
@@ -16,6 +81,7 @@ pub struct SetCacheRequest {
 pub struct CacheStatusResponse {
     status: String,
     item_count: usize,
+    stats: CacheStats,
     message: String,
 }
 
@@ -24,15 +90,18 @@ pub async fn set_cache(
     cache: web::Data<Cache>,
     request: web::Json<SetCacheRequest>,
 ) -> impl Responder {
-    match cache.set(&request.key, request.value.clone(), request.ttl) {
+    if request.tags.iter().any(|tag| tag.is_empty()) {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "标签不能为空字符串"
+        }));
+    }
+    match cache.set_tagged(&request.key, request.value.to_string(), request.ttl, &request.tags) {
         Ok(_) => HttpResponse::Ok().json(json!({
             "status": "success",
             "message": format!("缓存项 '{}' 设置成功", request.key)
         })),
-        Err(err) => HttpResponse::InternalServerError().json(json!({
-            "status": "error",
-            "message": format!("设置缓存失败: {}", err)
-        })),
+        Err(err) => cache_error_response("设置缓存失败", err),
     }
 }
 
@@ -43,11 +112,13 @@ pub async fn get_cache(
 ) -> impl Responder {
     let key = path.into_inner();
     match cache.get(&key) {
-        Ok(Some(value)) => HttpResponse::Ok().json(json!({
-            "status": "success",
-            "key": key,
-            "value": value
-        })),
+        Ok(Some(raw)) => {
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "key": key,
+                "value": decode_value(raw)
+            }))
+        },
         Ok(None) => HttpResponse::NotFound().json(json!({
             "status": "error",
             "message": format!("缓存项 '{}' 不存在", key)
@@ -85,11 +156,12 @@ pub async fn delete_cache(
 pub async fn get_cache_status(
     cache: web::Data<Cache>,
 ) -> impl Responder {
-    match cache.len() {
-        Ok(count) => {
+    match cache.stats() {
+        Ok(stats) => {
             let response = CacheStatusResponse {
                 status: "success".to_string(),
-                item_count: count,
+                item_count: stats.entries,
+                stats,
                 message: "缓存状态正常".to_string(),
             };
             HttpResponse::Ok().json(response)
@@ -115,4 +187,529 @@ pub async fn clear_cache(
             "message": format!("清空缓存失败: {}", err)
         })),
     }
-}
\ No newline at end of file
+}
+
+// 重置缓存统计计数
+pub async fn reset_cache_stats(
+    cache: web::Data<Cache>,
+) -> impl Responder {
+    cache.reset_stats();
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "message": "缓存统计已重置"
+    }))
+}
+
+// 列出命名空间及其缓存项数量
+pub async fn list_namespaces(
+    cache: web::Data<Cache>,
+) -> impl Responder {
+    match cache.namespaces() {
+        Ok(namespaces) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "namespaces": namespaces
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("获取命名空间失败: {}", err)
+        })),
+    }
+}
+
+// 设置命名空间的默认过期时间和容量
+pub async fn define_namespace(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+    request: web::Json<NamespaceConfig>,
+) -> impl Responder {
+    let name = path.into_inner();
+    let config = request.into_inner();
+    if name.contains(':') || config.default_ttl_secs == Some(0) || config.max_entries == Some(0) {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "命名空间名称不能包含 ':'，default_ttl_secs 和 max_entries 必须大于0"
+        }));
+    }
+    match cache.define_namespace(&name, config) {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("命名空间 '{}' 已更新", name)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("更新命名空间失败: {}", err)
+        })),
+    }
+}
+
+// 删除命名空间内的所有缓存项
+pub async fn clear_namespace(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let name = path.into_inner();
+    match cache.clear_namespace(&name) {
+        Ok(removed) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "removed": removed,
+            "message": format!("命名空间 '{}' 已清空", name)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("清空命名空间失败: {}", err)
+        })),
+    }
+}
+
+// 删除带有指定标签的所有缓存项
+pub async fn invalidate_tag(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let tag = path.into_inner();
+    match cache.invalidate_tag(&tag) {
+        Ok(removed) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "removed": removed,
+            "message": format!("标签 '{}' 的缓存项已删除", tag)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("删除标签缓存项失败: {}", err)
+        })),
+    }
+}
+
+// 其他模块直接写入的缓存项不一定是JSON，原样作为字符串返回
+fn decode_value(raw: String) -> Value {
+    serde_json::from_str::<Value>(&raw).unwrap_or(Value::String(raw))
+}
+
+fn batch_too_large(count: usize) -> Option<HttpResponse> {
+    (count > MAX_BATCH_KEYS).then(|| HttpResponse::BadRequest().json(json!({
+        "status": "error",
+        "message": format!("一次最多处理 {} 个键", MAX_BATCH_KEYS)
+    })))
+}
+
+// 批量获取缓存项，不存在的键列在 missing 中
+pub async fn mget_cache(
+    cache: web::Data<Cache>,
+    request: web::Json<CacheKeysRequest>,
+) -> impl Responder {
+    if let Some(response) = batch_too_large(request.keys.len()) {
+        return response;
+    }
+    let mut values = serde_json::Map::new();
+    let mut missing = Vec::new();
+    for key in &request.keys {
+        match cache.get(key) {
+            Ok(Some(raw)) => {
+                values.insert(key.clone(), decode_value(raw));
+            },
+            Ok(None) => missing.push(key.clone()),
+            Err(err) => return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("获取缓存失败: {}", err)
+            })),
+        }
+    }
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "values": values,
+        "missing": missing
+    }))
+}
+
+// 批量设置缓存项，逐个写入，部分失败时已写入的缓存项不会回滚
+pub async fn mset_cache(
+    cache: web::Data<Cache>,
+    request: web::Json<MsetCacheRequest>,
+) -> impl Responder {
+    if let Some(response) = batch_too_large(request.entries.len()) {
+        return response;
+    }
+    if request.entries.iter().any(|entry| entry.tags.iter().any(|tag| tag.is_empty())) {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "标签不能为空字符串"
+        }));
+    }
+    let mut written = 0;
+    let mut failed = Vec::new();
+    let mut internal_error = false;
+    for entry in &request.entries {
+        match cache.set_tagged(&entry.key, entry.value.to_string(), entry.ttl, &entry.tags) {
+            Ok(()) => written += 1,
+            Err(err) => {
+                internal_error |= matches!(err, CacheError::Internal(_));
+                failed.push(json!({ "key": entry.key, "message": err.to_string() }));
+            },
+        }
+    }
+    if failed.is_empty() {
+        HttpResponse::Ok().json(json!({
+            "status": "success",
+            "written": written,
+            "message": format!("已设置 {} 个缓存项", written)
+        }))
+    } else {
+        // 失败的缓存项都超过大小上限时返回413
+        let mut response = if internal_error { HttpResponse::InternalServerError() } else { HttpResponse::PayloadTooLarge() };
+        response.json(json!({
+            "status": "error",
+            "written": written,
+            "failed": failed,
+            "message": format!("{} 个缓存项设置失败", failed.len())
+        }))
+    }
+}
+
+// 批量删除缓存项，返回实际删除的数量
+pub async fn mdelete_cache(
+    cache: web::Data<Cache>,
+    request: web::Json<CacheKeysRequest>,
+) -> impl Responder {
+    if let Some(response) = batch_too_large(request.keys.len()) {
+        return response;
+    }
+    let mut removed = 0;
+    for key in &request.keys {
+        match cache.remove(key) {
+            Ok(true) => removed += 1,
+            Ok(false) => {},
+            Err(err) => return HttpResponse::InternalServerError().json(json!({
+                "status": "error",
+                "message": format!("删除缓存失败: {}", err)
+            })),
+        }
+    }
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "removed": removed,
+        "message": format!("已删除 {} 个缓存项", removed)
+    }))
+}
+
+// 按字典序分页列出键及其剩余过期时间，可按前缀和通配符过滤
+// 没有更多结果时 next_cursor 为空
+pub async fn scan_cache(
+    cache: web::Data<Cache>,
+    query: web::Query<ScanCacheQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_SCAN_LIMIT);
+    if limit == 0 || limit > MAX_SCAN_LIMIT {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": format!("limit 必须在 1 到 {} 之间", MAX_SCAN_LIMIT)
+        }));
+    }
+    let prefix = query.prefix.as_deref().unwrap_or("");
+    let filter = |key: &str| {
+        key.starts_with(prefix) && query.pattern.as_deref().is_none_or(|pattern| glob_match(pattern, key, ':'))
+    };
+    // 多取一个键用于判断是否还有下一页
+    match cache.scan(query.cursor.as_deref(), limit + 1, filter) {
+        Ok(mut keys) => {
+            let next_cursor = if keys.len() > limit {
+                keys.truncate(limit);
+                keys.last().map(|info| info.key.clone())
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(json!({
+                "status": "success",
+                "keys": keys,
+                "next_cursor": next_cursor
+            }))
+        },
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("列出缓存键失败: {}", err)
+        })),
+    }
+}
+
+// 获取缓存项的剩余过期时间，ttl 为空表示不过期
+pub async fn get_cache_ttl(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let key = path.into_inner();
+    match cache.ttl(&key) {
+        Ok(Some(ttl)) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "key": key,
+            "ttl": ttl,
+            "persistent": ttl.is_none()
+        })),
+        Ok(None) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("缓存项 '{}' 不存在", key)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("获取过期时间失败: {}", err)
+        })),
+    }
+}
+
+// 重新设置已有缓存项的过期时间
+pub async fn expire_cache(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+    request: web::Json<ExpireCacheRequest>,
+) -> impl Responder {
+    let key = path.into_inner();
+    if request.ttl == 0 {
+        return HttpResponse::BadRequest().json(json!({
+            "status": "error",
+            "message": "ttl 必须大于0，删除缓存项请使用删除接口"
+        }));
+    }
+    match cache.expire(&key, request.ttl) {
+        Ok(true) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("缓存项 '{}' 将在 {} 秒后过期", key, request.ttl)
+        })),
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("缓存项 '{}' 不存在", key)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("设置过期时间失败: {}", err)
+        })),
+    }
+}
+
+// 移除已有缓存项的过期时间
+pub async fn persist_cache(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let key = path.into_inner();
+    match cache.persist(&key) {
+        Ok(true) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("缓存项 '{}' 已设置为不过期", key)
+        })),
+        Ok(false) => HttpResponse::NotFound().json(json!({
+            "status": "error",
+            "message": format!("缓存项 '{}' 不存在", key)
+        })),
+        Err(err) => HttpResponse::InternalServerError().json(json!({
+            "status": "error",
+            "message": format!("移除过期时间失败: {}", err)
+        })),
+    }
+}
+
+// 计数器加上 delta，键不存在时从0开始
+pub async fn incr_cache(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+    request: web::Json<CounterRequest>,
+) -> impl Responder {
+    let key = path.into_inner();
+    counter_response(&key, cache.incr_by(&key, request.delta, request.ttl))
+}
+
+// 计数器减去 delta，键不存在时从0开始
+pub async fn decr_cache(
+    cache: web::Data<Cache>,
+    path: web::Path<String>,
+    request: web::Json<CounterRequest>,
+) -> impl Responder {
+    let key = path.into_inner();
+    counter_response(&key, cache.decr_by(&key, request.delta, request.ttl))
+}
+
+fn counter_response(key: &str, result: Result<i64, CacheError>) -> HttpResponse {
+    match result {
+        Ok(value) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "key": key,
+            "value": value
+        })),
+        Err(err) => cache_error_response("更新计数器失败", err),
+    }
+}
+
+// 写入或计数失败时的响应：当前值不是整数返回409，结果溢出返回422，缓存项过大返回413，内部错误返回500
+fn cache_error_response(action: &str, err: CacheError) -> HttpResponse {
+    let mut response = match err {
+        CacheError::NotInteger(_) => HttpResponse::Conflict(),
+        CacheError::Overflow(_) => HttpResponse::UnprocessableEntity(),
+        CacheError::TooLarge { .. } => HttpResponse::PayloadTooLarge(),
+        CacheError::Internal(_) => HttpResponse::InternalServerError(),
+    };
+    response.json(json!({
+        "status": "error",
+        "message": format!("{}: {}", action, err)
+    }))
+}
+
+// 键不存在时设置缓存项，已存在时返回409
+pub async fn set_cache_if_absent(
+    cache: web::Data<Cache>,
+    request: web::Json<SetIfAbsentRequest>,
+) -> impl Responder {
+    match cache.set_if_absent(&request.key, request.value.to_string(), request.ttl) {
+        Ok(true) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("缓存项 '{}' 设置成功", request.key)
+        })),
+        Ok(false) => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": format!("缓存项 '{}' 已存在", request.key)
+        })),
+        Err(err) => cache_error_response("设置缓存失败", err),
+    }
+}
+
+// 当前值等于期望值时替换缓存项，否则返回409
+pub async fn compare_and_swap_cache(
+    cache: web::Data<Cache>,
+    request: web::Json<CompareAndSwapRequest>,
+) -> impl Responder {
+    let expected = request.expected.to_string();
+    match cache.compare_and_swap(&request.key, &expected, request.value.to_string(), request.ttl) {
+        Ok(true) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "message": format!("缓存项 '{}' 已替换", request.key)
+        })),
+        Ok(false) => HttpResponse::Conflict().json(json!({
+            "status": "error",
+            "message": format!("缓存项 '{}' 不存在或当前值与期望值不一致", request.key)
+        })),
+        Err(err) => cache_error_response("替换缓存失败", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::App;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use super::*;
+
+    // 每个分片最多 256 字节，便于构造超过大小上限的缓存项
+    fn routes(cfg: &mut web::ServiceConfig) {
+        let cache: Cache = Arc::new(SimpleCache::new(CacheConfig { max_bytes: 1024, shards: 4, ..CacheConfig::default() }));
+        cfg.app_data(web::Data::new(cache))
+            .route("/set", web::post().to(set_cache))
+            .route("/mset", web::post().to(mset_cache))
+            .route("/set-if-absent", web::post().to(set_cache_if_absent))
+            .route("/compare-and-swap", web::post().to(compare_and_swap_cache))
+            .route("/incr/{key}", web::post().to(incr_cache))
+            .route("/decr/{key}", web::post().to(decr_cache))
+            .route("/keys", web::get().to(scan_cache))
+            .route("/ttl/{key}", web::get().to(get_cache_ttl))
+            .route("/expire/{key}", web::post().to(expire_cache))
+            .route("/persist/{key}", web::post().to(persist_cache));
+    }
+
+    fn post(path: &str, body: Value) -> actix_web::test::TestRequest {
+        actix_web::test::TestRequest::post().uri(path).set_json(body)
+    }
+
+    #[actix_web::test]
+    async fn counter_errors_are_client_errors() {
+        let app = actix_web::test::init_service(App::new().configure(routes)).await;
+        let status = |res: actix_web::dev::ServiceResponse| res.status();
+
+        assert_eq!(status(actix_web::test::call_service(&app, post("/set", json!({ "key": "name", "value": "abc" })).to_request()).await), StatusCode::OK);
+        assert_eq!(status(actix_web::test::call_service(&app, post("/incr/name", json!({})).to_request()).await), StatusCode::CONFLICT);
+
+        assert_eq!(status(actix_web::test::call_service(&app, post("/incr/count", json!({ "delta": i64::MAX })).to_request()).await), StatusCode::OK);
+        assert_eq!(status(actix_web::test::call_service(&app, post("/incr/count", json!({ "delta": 1 })).to_request()).await), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(status(actix_web::test::call_service(&app, post("/decr/other", json!({ "delta": i64::MIN })).to_request()).await), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let res = actix_web::test::call_service(&app, post("/decr/count", json!({ "delta": 2 })).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["value"], json!(i64::MAX - 2));
+    }
+
+    #[actix_web::test]
+    async fn oversize_items_are_rejected_with_413() {
+        let app = actix_web::test::init_service(App::new().configure(routes)).await;
+        let large = "x".repeat(512);
+
+        for (path, body) in [
+            ("/set", json!({ "key": "large", "value": large })),
+            ("/set-if-absent", json!({ "key": "large", "value": large })),
+            ("/mset", json!({ "entries": [{ "key": "small", "value": 1 }, { "key": "large", "value": large }] })),
+        ] {
+            let res = actix_web::test::call_service(&app, post(path, body).to_request()).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE, "{}", path);
+        }
+
+        assert_eq!(actix_web::test::call_service(&app, post("/set", json!({ "key": "cas", "value": 1 })).to_request()).await.status(), StatusCode::OK);
+        let res = actix_web::test::call_service(&app, post("/compare-and-swap", json!({ "key": "cas", "expected": 1, "value": large })).to_request()).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    async fn call_json<S, R, B>(app: &S, req: R) -> (StatusCode, Value)
+    where
+        S: Service<R, Response = actix_web::dev::ServiceResponse<B>, Error = actix_web::Error>,
+        B: actix_web::body::MessageBody,
+    {
+        let res = app.call(req).await.unwrap();
+        let status = res.status();
+        (status, serde_json::from_slice(&actix_web::test::read_body(res).await).unwrap_or(Value::Null))
+    }
+
+    fn get(path: &str) -> actix_web::test::TestRequest {
+        actix_web::test::TestRequest::get().uri(path)
+    }
+
+    #[actix_web::test]
+    async fn scan_pages_through_keys_matching_a_pattern() {
+        let app = actix_web::test::init_service(App::new().configure(routes)).await;
+        for key in ["user:1:name", "user:1:email", "user:2:name", "user:10:name", "user:1:profile:name", "session:1"] {
+            assert_eq!(actix_web::test::call_service(&app, post("/set", json!({ "key": key, "value": 1 })).to_request()).await.status(), StatusCode::OK);
+        }
+
+        // `*` 不匹配 ':'，user:1:profile:name 不在结果中
+        let (status, body) = call_json(&app, get("/keys?pattern=user:*:name&limit=2").to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["keys"], json!([{ "key": "user:10:name", "ttl": null }, { "key": "user:1:name", "ttl": null }]));
+        assert_eq!(body["next_cursor"], "user:1:name");
+
+        let (_, body) = call_json(&app, get("/keys?pattern=user:*:name&limit=2&cursor=user:1:name").to_request()).await;
+        assert_eq!(body["keys"], json!([{ "key": "user:2:name", "ttl": null }]));
+        assert!(body["next_cursor"].is_null());
+
+        let (_, body) = call_json(&app, get("/keys?pattern=user:**:name&prefix=user:1:").to_request()).await;
+        assert_eq!(body["keys"], json!([{ "key": "user:1:name", "ttl": null }, { "key": "user:1:profile:name", "ttl": null }]));
+
+        assert_eq!(call_json(&app, get("/keys?limit=0").to_request()).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn ttl_expire_and_persist_routes() {
+        let app = actix_web::test::init_service(App::new().configure(routes)).await;
+        assert_eq!(call_json(&app, get("/ttl/missing").to_request()).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(call_json(&app, post("/expire/missing", json!({ "ttl": 30 })).to_request()).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(call_json(&app, post("/persist/missing", json!({})).to_request()).await.0, StatusCode::NOT_FOUND);
+
+        call_json(&app, post("/set", json!({ "key": "session", "value": 1 })).to_request()).await;
+        let (status, body) = call_json(&app, get("/ttl/session").to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["ttl"].clone(), body["persistent"].clone()), (Value::Null, json!(true)));
+
+        assert_eq!(call_json(&app, post("/expire/session", json!({ "ttl": 0 })).to_request()).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(call_json(&app, post("/expire/session", json!({ "ttl": 30 })).to_request()).await.0, StatusCode::OK);
+        let (_, body) = call_json(&app, get("/ttl/session").to_request()).await;
+        assert!(body["ttl"].as_u64().is_some_and(|ttl| (29..=30).contains(&ttl)));
+        assert_eq!(body["persistent"], json!(false));
+
+        assert_eq!(call_json(&app, post("/persist/session", json!({})).to_request()).await.0, StatusCode::OK);
+        let (_, body) = call_json(&app, get("/ttl/session").to_request()).await;
+        assert_eq!(body["persistent"], json!(true));
+    }
+}
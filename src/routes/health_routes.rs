@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use actix_web::{web, HttpResponse, Responder};
+use mysql::prelude::Queryable;
+use serde::Serialize;
+use serde_json::json;
+use deadpool_redis::redis::cmd;
+use crate::config;
+use crate::db::DbPool;
+use crate::middleware::JsonLogger;
+use crate::rbatis_pool::RBATIS_POOL;
+use crate::redis_pool::RedisPool;
+
+// 健康状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthStatus {
+    Up,
+    Degraded,
+    Down,
+}
+
+// 单个依赖组件的检查结果
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub required: bool,       // 必需组件不可用时服务判定为未就绪
+    pub latency_ms: u128,
+    pub error: Option<String>,
+}
+
+// 就绪检查响应
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    pub status: HealthStatus,
+    pub components: Vec<ComponentHealth>,
+}
+
+impl ComponentHealth {
+    fn from_result(name: &'static str, required: bool, started: Instant, result: Result<(), String>) -> Self {
+        let latency_ms = started.elapsed().as_millis();
+        match result {
+            Ok(()) => Self { name, status: HealthStatus::Up, required, latency_ms, error: None },
+            Err(err) => Self { name, status: HealthStatus::Down, required, latency_ms, error: Some(err) },
+        }
+    }
+}
+
+// 给探测加上超时，超时视为失败
+async fn with_timeout<F>(timeout: Duration, probe: F) -> Result<(), String>
+where
+    F: Future<Output = Result<(), String>>,
+{
+    match tokio::time::timeout(timeout, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("探测超时（{}ms）", timeout.as_millis())),
+    }
+}
+
+// 探测 r2d2 MySQL 连接池
+async fn check_database(pool: DbPool, timeout: Duration) -> ComponentHealth {
+    let started = Instant::now();
+    let result = with_timeout(timeout, async move {
+        web::block(move || -> Result<(), String> {
+            let mut conn = pool.get_timeout(timeout).map_err(|e| format!("获取数据库连接失败: {}", e))?;
+            conn.query_drop("SELECT 1").map_err(|e| format!("数据库查询失败: {}", e))
+        })
+        .await
+        .map_err(|e| format!("数据库探测任务失败: {}", e))?
+    }).await;
+    ComponentHealth::from_result("database", true, started, result)
+}
+
+// 探测 rbatis 连接池
+async fn check_rbatis(timeout: Duration) -> ComponentHealth {
+    let started = Instant::now();
+    let result = with_timeout(timeout, async {
+        RBATIS_POOL.exec("SELECT 1", vec![])
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("rbatis查询失败: {}", e))
+    }).await;
+    ComponentHealth::from_result("rbatis", true, started, result)
+}
+
+// 探测 Redis 连接池，连接池未初始化时直接判定为不可用
+async fn check_redis(pool: &RedisPool, timeout: Duration, required: bool) -> ComponentHealth {
+    let started = Instant::now();
+    let result = match pool.as_ref() {
+        Some(pool_inner) => with_timeout(timeout, async {
+            let mut conn = pool_inner.get().await.map_err(|e| format!("获取Redis连接失败: {}", e))?;
+            let _: String = cmd("PING")
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| format!("Redis PING失败: {}", e))?;
+            Ok(())
+        }).await,
+        None => Err(String::from("Redis连接池未初始化")),
+    };
+    ComponentHealth::from_result("redis", required, started, result)
+}
+
+// 检查JSON日志文件写入器
+fn check_logger(logger: &Arc<Mutex<JsonLogger>>) -> ComponentHealth {
+    let started = Instant::now();
+    let result = match logger.lock() {
+        Ok(logger_guard) => logger_guard.check_writer().map_err(|e| format!("日志写入失败: {}", e)),
+        Err(_) => Err(String::from("日志器锁已损坏")),
+    };
+    ComponentHealth::from_result("json_logger", true, started, result)
+}
+
+// 汇总各组件状态：必需组件不可用为DOWN，可选组件不可用为DEGRADED
+fn overall_status(components: &[ComponentHealth]) -> HealthStatus {
+    let mut status = HealthStatus::Up;
+    for component in components {
+        if component.status == HealthStatus::Up {
+            continue;
+        }
+        if component.required {
+            return HealthStatus::Down;
+        }
+        status = HealthStatus::Degraded;
+    }
+    status
+}
+
+// 存活检查：进程能响应请求即为存活，不探测依赖
+pub async fn liveness() -> impl Responder {
+    HttpResponse::Ok().json(json!({
+        "status": HealthStatus::Up,
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+// 就绪检查：探测所有依赖，任一必需组件不可用时返回503
+pub async fn readiness(
+    pool: web::Data<DbPool>,
+    redis_pool: web::Data<RedisPool>,
+    logger: web::Data<Arc<Mutex<JsonLogger>>>,
+) -> impl Responder {
+    let health_config = &config::get().health;
+    let timeout = Duration::from_millis(health_config.timeout_ms);
+
+    let (database, rbatis, redis) = futures::join!(
+        check_database(pool.get_ref().clone(), timeout),
+        check_rbatis(timeout),
+        check_redis(redis_pool.get_ref(), timeout, health_config.redis_required),
+    );
+    let components = vec![database, rbatis, redis, check_logger(logger.get_ref())];
+    let status = overall_status(&components);
+
+    let response = ReadinessResponse { status, components };
+    if status == HealthStatus::Down {
+        HttpResponse::ServiceUnavailable().json(response)
+    } else {
+        HttpResponse::Ok().json(response)
+    }
+}
+
+// 单独探测 rbatis 连接池
+pub async fn rbatis_readiness() -> ComponentHealth {
+    check_rbatis(Duration::from_millis(config::get().health.timeout_ms)).await
+}
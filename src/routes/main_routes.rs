@@ -1,106 +1,29 @@
 use std::sync::{Arc, Mutex};
 use actix_web::{HttpResponse, Responder, web, HttpRequest};
-use log::logger;
 use crate::db::{DbPool, User, CreateUserRequest, UpdateUserRequest, ApiResponse, get_connection_or_return_error};
 use mysql::prelude::Queryable; 
 use serde_json;
 use serde_json::json;
-use crate::middleware::{JsonLogger, LogLevel};
+use log::error;
+use crate::layered_cache::LayeredCache;
+use crate::middleware::{Authorize, JsonLogger, LogLevel, RateLimit, ResponseCache};
 // 导入rbatis_routes模块以使用其中的方法
-use crate::routes::{rbatis_routes,auth_routes,cache_routes,redis_routes};
-// 导入其他模块需要的类型
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use log::{info, error};
-use crate::middleware::JwtMiddleware;
-use crate::cache::Cache;
-use std::sync::Arc as StdArc;
-use crate::redis_pool;
-use crate::rbatis_pool::RBATIS_POOL;
-use rbatis::crud;
-use rbs::value::Value;
+use crate::routes::{rbatis_routes,auth_routes,cache_routes,redis_routes,health_routes,api_key_routes};
 
-// 登录请求结构体
-#[derive(Debug, Deserialize)]
-pub struct LoginRequest {
-    pub phone: String, 
-    pub password: String,
-}
-
-// 登录响应结构体
-#[derive(Debug, Serialize)]
-pub struct LoginResponse {
-    pub token: String, 
-    pub token_type: String, 
-    pub expires_in: u64, 
-    pub user_id: u64, 
-    pub phone: String, 
-}
-
-// 注册请求结构体
-#[derive(Debug, Deserialize)]
-pub struct RegisterRequest {
-    pub phone: String, 
-    pub password: String, 
-    pub name: Option<String>, 
-}
-
-// 注册响应结构体
-#[derive(Debug, Serialize)]
-pub struct RegisterResponse {
-    pub success: bool, 
-    pub user_id: u64, 
-    pub phone: String, 
-}
-
-// 设置缓存请求结构
-#[derive(Debug, Deserialize)]
-pub struct SetCacheRequest {
-    key: String,
-    value: String,
-    ttl: Option<u64>,
-}
-
-// 缓存状态响应结构
-#[derive(Debug, Serialize)]
-pub struct CacheStatusResponse {
-    status: String,
-    item_count: usize,
-    message: String,
-}
-
-// Redis操作请求体结构
-#[derive(Debug, Deserialize)]
-pub struct RedisSetRequest {
-    key: String,
-    value: String,
-    expiry_seconds: Option<u64>
-}
+// 用户记录的缓存时间（秒）
+const USER_CACHE_TTL_SECS: u64 = 60;
 
-// Redis操作响应结构
-#[derive(Debug, Serialize)]
-pub struct RedisResponse {
-    status: String,
-    message: String,
-    data: Option<String>
+fn user_cache_key(user_id: u64) -> String {
+    format!("user:{}", user_id)
 }
 
-// Rbatis User 结构体
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct RbatisUser {
-    pub id: Option<u64>,
-    pub phone: Option<String>,
-    pub name: Option<String>,
-    pub avatar: Option<u8>,
-    pub create_time: Option<u32>,
-    pub first_change: Option<u8>,
-    pub is_business: Option<u8>,
-    pub is_ban: Option<u8>,
+// 用户被修改或删除后使缓存失效
+async fn invalidate_cached_user(cache: &LayeredCache, user_id: u64) {
+    if let Err(e) = cache.remove(&user_cache_key(user_id)).await {
+        error!("删除用户缓存失败: {}, user_id={}", e, user_id);
+    }
 }
 
-// 为 RbatisUser 自动生成 CRUD 方法
-crud!(RbatisUser{});
-
 // 健康检查路由处理函数
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok().json(serde_json::json!({
@@ -117,7 +40,7 @@ pub async fn create_user(
 ) -> Result<impl Responder, actix_web::Error> {
     let mut conn = get_connection_or_return_error(&pool).await?;
     
-    let _affected_rows = conn.exec_drop(
+    conn.exec_drop(
         "INSERT INTO user (phone, name, avatar, createTime, firstChange, isBusiness, isBan) VALUES (?, ?, ?, UNIX_TIMESTAMP(), 1, 0, 0)",
         (&user.phone, &user.name, &user.avatar.unwrap_or(0))
     ).map_err(|e| {
@@ -153,19 +76,36 @@ pub async fn get_users(
     Ok(HttpResponse::Ok().json(response))
 }
 
-// 根据ID获取用户处理函数
+// 根据ID获取用户处理函数，用户记录在两级缓存中保存一段时间
 pub async fn get_user_by_id(
     pool: web::Data<DbPool>,
+    cache: web::Data<LayeredCache>,
     user_id: web::Path<u64>,
 ) -> Result<impl Responder, actix_web::Error> {
-    let mut conn = get_connection_or_return_error(&pool).await?;
-    
-    let user: Option<User> = conn.exec_first(
-        "SELECT * FROM user WHERE id = ?",
-        (user_id.into_inner(),)
-    ).map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Failed to get user: {}", e))
-    })?;
+    let user_id = user_id.into_inner();
+    let cache_key = user_cache_key(user_id);
+    let cached: Option<User> = cache.get_typed(&cache_key).await.unwrap_or_else(|e| {
+        error!("读取用户缓存失败: {}", e);
+        None
+    });
+
+    let user = match cached {
+        Some(user) => Some(user),
+        None => {
+            let mut conn = get_connection_or_return_error(&pool).await?;
+            let user: Option<User> = conn.exec_first(
+                "SELECT * FROM user WHERE id = ?",
+                (user_id,)
+            ).map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("Failed to get user: {}", e))
+            })?;
+            if let Some(user) = &user
+                && let Err(e) = cache.set_typed(&cache_key, user, Some(USER_CACHE_TTL_SECS)).await {
+                error!("写入用户缓存失败: {}", e);
+            }
+            user
+        },
+    };
     
     match user {
         Some(found_user) => {
@@ -192,6 +132,7 @@ pub async fn get_user_by_id(
 // 更新用户处理函数
 pub async fn update_user(
     pool: web::Data<DbPool>,
+    cache: web::Data<LayeredCache>,
     user_id: web::Path<u64>,
     update_data: web::Json<UpdateUserRequest>,
 ) -> Result<impl Responder, actix_web::Error> {
@@ -218,12 +159,13 @@ pub async fn update_user(
     }
     
     // 执行更新
-    let _affected_rows = conn.exec_drop(
+    conn.exec_drop(
         "UPDATE user SET name = ?, avatar = ?, firstChange = ?, isBusiness = ?, isBan = ? WHERE id = ?",
         (&update_data.name, &update_data.avatar.unwrap_or(0), &update_data.first_change.unwrap_or(0), &update_data.is_business.unwrap_or(0), &update_data.is_ban.unwrap_or(0), user_id_value)
     ).map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Failed to update user: {}", e))
     })?;
+    invalidate_cached_user(&cache, user_id_value).await;
     
     let response = ApiResponse {
         message: "User updated successfully".to_string(),
@@ -236,6 +178,7 @@ pub async fn update_user(
 // 删除用户处理函数
 pub async fn delete_user(
     pool: web::Data<DbPool>,
+    cache: web::Data<LayeredCache>,
     user_id: web::Path<u64>,
 ) -> Result<impl Responder, actix_web::Error> {
     let mut conn = get_connection_or_return_error(&pool).await?;
@@ -261,12 +204,13 @@ pub async fn delete_user(
     }
     
     // 执行删除
-    let _affected_rows = conn.exec_drop(
+    conn.exec_drop(
         "DELETE FROM user WHERE id = ?",
         (user_id_value,)
     ).map_err(|e| {
         actix_web::error::ErrorInternalServerError(format!("Failed to delete user: {}", e))
     })?;
+    invalidate_cached_user(&cache, user_id_value).await;
     
     let response = ApiResponse {
         message: "User deleted successfully".to_string(),
@@ -316,41 +260,77 @@ pub async fn json_logger(
     }))
 }
 
+// 用户列表和详情的响应缓存分组，用户的增删改请求成功后失效
+const USERS_RESPONSE_CACHE: &str = "users";
+// 用户列表包含全部用户，其他实例的修改只能等缓存过期后可见，缓存时间短于详情
+const USER_LIST_CACHE_TTL_SECS: u64 = 10;
+
 // 配置主要路由
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
+            .wrap(RateLimit::scope("api"))
             .route("/health", web::get().to(health_check))
-            .route("/users", web::post().to(create_user))
-            .route("/users", web::get().to(get_users))
-            .route("/users/{id}", web::get().to(get_user_by_id))
-            .route("/users/{id}", web::put().to(update_user))
-            .route("/users/{id}", web::delete().to(delete_user))
+            .route("/health/live", web::get().to(health_routes::liveness))
+            .route("/health/ready", web::get().to(health_routes::readiness))
+            .route("/users", web::post().to(create_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:write")))
+            .route("/users", web::get().to(get_users).wrap(ResponseCache::group(USERS_RESPONSE_CACHE).ttl(USER_LIST_CACHE_TTL_SECS)).wrap(Authorize::permission("users:read")))
+            .route("/users/{id}", web::get().to(get_user_by_id).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:read")))
+            .route("/users/{id}", web::put().to(update_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:write")))
+            .route("/users/{id}", web::delete().to(delete_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:delete")))
             .route("/logger", web::get().to(json_logger))
     ).service(
         web::scope("/rbatis")
+            .wrap(RateLimit::scope("rbatis"))
             .route("/health", web::get().to(rbatis_routes::rbatis_health_check))
-            .route("/users", web::get().to(rbatis_routes::rbatis_get_users))
-            .route("/users", web::post().to(rbatis_routes::rbatis_create_user))
-            .route("/users/{id}", web::get().to(rbatis_routes::rbatis_get_user_by_id))
-            .route("/users/{id}", web::put().to(rbatis_routes::rbatis_update_user))
-            .route("/users/{id}", web::delete().to(rbatis_routes::rbatis_delete_user))
+            .route("/users", web::get().to(rbatis_routes::rbatis_get_users).wrap(ResponseCache::group(USERS_RESPONSE_CACHE).ttl(USER_LIST_CACHE_TTL_SECS)).wrap(Authorize::permission("users:read")))
+            .route("/users", web::post().to(rbatis_routes::rbatis_create_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:write")))
+            .route("/users/{id}", web::get().to(rbatis_routes::rbatis_get_user_by_id).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:read")))
+            .route("/users/{id}", web::put().to(rbatis_routes::rbatis_update_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:write")))
+            .route("/users/{id}", web::delete().to(rbatis_routes::rbatis_delete_user).wrap(ResponseCache::group(USERS_RESPONSE_CACHE)).wrap(Authorize::permission("users:delete")))
     )
         .service(
         web::scope("/auth")
             .route("/login", web::post().to(auth_routes::login))
             .route("/register", web::post().to(auth_routes::register))
+            .route("/refresh", web::post().to(auth_routes::refresh))
+            .route("/logout", web::post().to(auth_routes::logout))
+            .route("/logout/all", web::post().to(auth_routes::logout_all))
             .route("/me", web::get().to(auth_routes::get_current_user))
     ).service(
         web::scope("/cache")
-            .route("/set", web::post().to(cache_routes::set_cache))
-            .route("/get/{key}", web::get().to(cache_routes::get_cache))
-            .route("/delete/{key}", web::delete().to(cache_routes::delete_cache))
-            .route("/status", web::get().to(cache_routes::get_cache_status))
-            .route("/clear", web::delete().to(cache_routes::clear_cache))
+            .wrap(RateLimit::scope("cache"))
+            .route("/set", web::post().to(cache_routes::set_cache).wrap(Authorize::permission("cache:write")))
+            .route("/get/{key}", web::get().to(cache_routes::get_cache).wrap(Authorize::permission("cache:read")))
+            .route("/delete/{key}", web::delete().to(cache_routes::delete_cache).wrap(Authorize::permission("cache:write")))
+            .route("/set-if-absent", web::post().to(cache_routes::set_cache_if_absent).wrap(Authorize::permission("cache:write")))
+            .route("/compare-and-swap", web::post().to(cache_routes::compare_and_swap_cache).wrap(Authorize::permission("cache:write")))
+            .route("/incr/{key}", web::post().to(cache_routes::incr_cache).wrap(Authorize::permission("cache:write")))
+            .route("/decr/{key}", web::post().to(cache_routes::decr_cache).wrap(Authorize::permission("cache:write")))
+            .route("/mget", web::post().to(cache_routes::mget_cache).wrap(Authorize::permission("cache:read")))
+            .route("/mset", web::post().to(cache_routes::mset_cache).wrap(Authorize::permission("cache:write")))
+            .route("/mdelete", web::post().to(cache_routes::mdelete_cache).wrap(Authorize::permission("cache:write")))
+            .route("/keys", web::get().to(cache_routes::scan_cache).wrap(Authorize::permission("cache:read")))
+            .route("/ttl/{key}", web::get().to(cache_routes::get_cache_ttl).wrap(Authorize::permission("cache:read")))
+            .route("/expire/{key}", web::post().to(cache_routes::expire_cache).wrap(Authorize::permission("cache:write")))
+            .route("/persist/{key}", web::post().to(cache_routes::persist_cache).wrap(Authorize::permission("cache:write")))
+            .route("/status", web::get().to(cache_routes::get_cache_status).wrap(Authorize::permission("cache:read")))
+            .route("/clear", web::delete().to(cache_routes::clear_cache).wrap(Authorize::role("admin")))
+            .route("/stats/reset", web::post().to(cache_routes::reset_cache_stats).wrap(Authorize::role("admin")))
+            .route("/namespaces", web::get().to(cache_routes::list_namespaces).wrap(Authorize::permission("cache:read")))
+            .route("/namespaces/{name}", web::put().to(cache_routes::define_namespace).wrap(Authorize::role("admin")))
+            .route("/namespaces/{name}", web::delete().to(cache_routes::clear_namespace).wrap(Authorize::permission("cache:write")))
+            .route("/tags/{tag}", web::delete().to(cache_routes::invalidate_tag).wrap(Authorize::permission("cache:write")))
     ).service(
         web::scope("/redis")
-            .route("/{key}", web::get().to(redis_routes::redis_get))
-            .route("/set", web::post().to(redis_routes::redis_set))
-    );
+            .wrap(RateLimit::scope("redis"))
+            .route("/{key}", web::get().to(redis_routes::redis_get).wrap(Authorize::permission("redis:read")))
+            .route("/set", web::post().to(redis_routes::redis_set).wrap(Authorize::permission("redis:write")))
+    ).service(
+        web::scope("/admin")
+            .wrap(Authorize::role("admin"))
+            .route("/api-keys", web::post().to(api_key_routes::create_api_key))
+            .route("/api-keys", web::get().to(api_key_routes::list_api_keys))
+            .route("/api-keys/{id}", web::delete().to(api_key_routes::revoke_api_key))
+    ).route("/.well-known/jwks.json", web::get().to(auth_routes::jwks));
 }
\ No newline at end of file
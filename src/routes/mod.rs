@@ -6,6 +6,10 @@ pub mod auth_routes;  // 新的认证路由
 pub mod cache_routes; // 缓存相关路由
 pub mod redis_routes; // Redis操作路由
 pub mod rbatis_routes; // Rbatis路由
+pub mod health_routes; // 存活与就绪检查路由
+pub mod api_key_routes; // API密钥管理路由
+#[cfg(test)]
+pub(crate) mod test_support; // 测试共用的辅助函数
 
 // 配置所有路由
 pub fn config(cfg: &mut web::ServiceConfig) {
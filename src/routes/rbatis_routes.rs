@@ -1,6 +1,7 @@
 use actix_web::{HttpResponse, Responder, web};
 use serde::{Deserialize, Serialize};
 use crate::rbatis_pool::RBATIS_POOL;
+use crate::routes::health_routes::{self, HealthStatus};
 use rbatis::crud;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -18,13 +19,24 @@ pub struct User {
 // 自动生成 CRUD 方法
 crud!(User{});
 
-// 健康检查路由处理函数
+// 健康检查路由处理函数，实际探测 rbatis 连接池
 pub async fn rbatis_health_check() -> impl Responder {
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": "UP",
-        "service": "rbatis",
-        "message": "Rbatis service is running normally"
-    }))
+    let component = health_routes::rbatis_readiness().await;
+    if component.status == HealthStatus::Up {
+        HttpResponse::Ok().json(serde_json::json!({
+            "status": "UP",
+            "service": "rbatis",
+            "latency_ms": component.latency_ms,
+            "message": "Rbatis service is running normally"
+        }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "status": "DOWN",
+            "service": "rbatis",
+            "latency_ms": component.latency_ms,
+            "message": component.error
+        }))
+    }
 }
 
 // 获取所有用户处理函数
@@ -1,7 +1,5 @@
 use actix_web::{web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::sync::Arc;
 use crate::redis_pool;
 
 // Redis操作请求体结构
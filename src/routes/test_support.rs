@@ -0,0 +1,54 @@
+// 测试共用的辅助函数
+// 标记为 ignore 的测试需要 config/test.toml 中的测试数据库（已执行 migrations 下的脚本）或Redis，运行方式:
+// APP_PROFILE=test cargo test -- --ignored
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use mysql::prelude::Queryable;
+use crate::config::{self, AppConfig, Profile};
+use crate::db::{DbPool, grant_role, init_db_pool};
+use crate::redis_pool::{RedisPool, init_redis_pool};
+use crate::utils::time::unix_now_millis;
+
+// 加载测试环境配置并设为全局配置，只允许在 test 环境下连接数据库
+pub fn app_config() -> &'static AppConfig {
+    static APP_CONFIG: OnceLock<&'static AppConfig> = OnceLock::new();
+    APP_CONFIG.get_or_init(|| {
+        let app_config = config::init(AppConfig::load().expect("加载测试配置失败"));
+        assert_eq!(app_config.profile, Profile::Test, "数据库测试只能在 APP_PROFILE=test 下运行");
+        app_config
+    })
+}
+
+// 测试数据库的连接池，所有测试共用
+pub fn db_pool() -> DbPool {
+    static POOL: OnceLock<DbPool> = OnceLock::new();
+    POOL.get_or_init(|| init_db_pool(&app_config().database).expect("连接测试数据库失败")).clone()
+}
+
+// 测试Redis的连接池
+pub fn redis_pool() -> RedisPool {
+    init_redis_pool(&app_config().redis).expect("创建测试Redis连接池失败")
+}
+
+// 每次调用生成不同的手机号，避免测试之间互相影响
+pub fn unique_phone(prefix: &str) -> String {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    format!("{}{:06}{:03}", prefix, unix_now_millis() % 1_000_000, sequence % 1000)
+}
+
+// 直接写库创建用户并授予角色，返回用户ID
+pub fn create_user(pool: &DbPool, phone: &str, role: &str) -> u64 {
+    let mut conn = pool.get().unwrap();
+    conn.exec_drop("INSERT INTO users (phone, password, name) VALUES (?, ?, ?)", (phone, "", "test")).unwrap();
+    let user_id = conn.last_insert_id();
+    grant_role(&mut conn, user_id, role).unwrap();
+    user_id
+}
+
+// 删除用户及其角色
+pub fn delete_user(pool: &DbPool, phone: &str) {
+    let mut conn = pool.get().unwrap();
+    conn.exec_drop("DELETE ur FROM user_roles ur JOIN users u ON u.id = ur.user_id WHERE u.phone = ?", (phone,)).unwrap();
+    conn.exec_drop("DELETE FROM users WHERE phone = ?", (phone,)).unwrap();
+}
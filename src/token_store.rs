@@ -0,0 +1,309 @@
+use std::fmt;
+use std::time::Duration;
+use deadpool_redis::Connection;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::cache::Cache;
+use crate::redis_pool::{self, RedisPool};
+use crate::utils::time::{unix_now, unix_now_millis};
+use crate::utils::token::{hash_token, random_token};
+
+// 存储键前缀
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token:";
+const REFRESH_TOKEN_USED_PREFIX: &str = "refresh_token_used:";
+const REFRESH_FAMILY_REVOKED_PREFIX: &str = "refresh_family_revoked:";
+const REVOKED_JTI_PREFIX: &str = "revoked_jti:";
+const SESSIONS_REVOKED_BEFORE_PREFIX: &str = "sessions_revoked_before:";
+
+// 令牌存储错误
+#[derive(Debug)]
+pub enum TokenStoreError {
+    InvalidToken,        // 令牌不存在或已过期
+    Revoked,             // 令牌所属的令牌族已被吊销
+    ReuseDetected,       // 已轮换过的令牌被再次使用，整个令牌族已被吊销
+    Backend(String),
+}
+
+impl fmt::Display for TokenStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenStoreError::InvalidToken => write!(f, "Invalid or expired refresh token"),
+            TokenStoreError::Revoked => write!(f, "Refresh token has been revoked"),
+            TokenStoreError::ReuseDetected => write!(f, "Refresh token reuse detected"),
+            TokenStoreError::Backend(msg) => write!(f, "Token store error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TokenStoreError {}
+
+// 服务端保存的刷新令牌信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenRecord {
+    pub user_id: u64,
+    pub subject: String,
+    pub family_id: String, // 同一次登录轮换出的所有刷新令牌属于同一个令牌族
+    #[serde(default)]
+    pub issued_at_ms: u64, // 签发时间（毫秒），用于判断是否早于“登出所有会话”
+    pub expires_at: u64,
+    #[serde(default)]
+    pub rotated: bool, // 已轮换，记录只作为墓碑保留到令牌过期，用于重用检测
+}
+
+// 新签发的刷新令牌
+#[derive(Debug)]
+pub struct IssuedRefreshToken {
+    pub token: String, // 返回给客户端的不透明令牌，服务端只保存其哈希
+    pub record: RefreshTokenRecord,
+}
+
+// 令牌存储：配置了Redis时只使用Redis，Redis出错时返回错误；未配置Redis时使用进程内缓存
+// 后端在启动时确定，运行中不会切换，避免Redis短暂故障时读到空的进程内缓存
+// 进程内缓存必须不淘汰缓存项（见 CacheConfig::unbounded），吊销记录被淘汰后已登出的令牌会重新生效
+pub struct TokenStore {
+    redis: RedisPool,
+    cache: Cache,
+    refresh_ttl: Duration,
+}
+
+impl TokenStore {
+    pub fn new(redis: RedisPool, cache: Cache, refresh_ttl: Duration) -> Self {
+        if redis.is_none() {
+            warn!("未配置Redis，令牌存储使用进程内缓存，多个实例之间不共享吊销记录");
+        }
+        Self {
+            redis,
+            cache,
+            refresh_ttl,
+        }
+    }
+
+    // 签发新的刷新令牌，开启一个新的令牌族
+    pub async fn issue_refresh_token(&self, user_id: u64, subject: String) -> Result<IssuedRefreshToken, TokenStoreError> {
+        self.issue_in_family(user_id, subject, random_token()).await
+    }
+
+    // 轮换刷新令牌：旧令牌作废并签发同一令牌族的新令牌
+    // 旧令牌的记录替换为墓碑，已轮换过的令牌再次出现说明令牌可能泄露，吊销整个令牌族
+    pub async fn rotate_refresh_token(&self, token: &str) -> Result<IssuedRefreshToken, TokenStoreError> {
+        let token_hash = hash_token(token);
+        let record_key = format!("{}{}", REFRESH_TOKEN_PREFIX, token_hash);
+        let record: RefreshTokenRecord = match self.get(&record_key).await? {
+            Some(raw) => serde_json::from_str(&raw).map_err(|e| TokenStoreError::Backend(e.to_string()))?,
+            None => return Err(TokenStoreError::InvalidToken),
+        };
+        if record.rotated {
+            return Err(self.reuse_detected(&record).await);
+        }
+
+        let now = unix_now();
+        if record.expires_at <= now {
+            return Err(TokenStoreError::InvalidToken);
+        }
+        if self.is_family_revoked(&record.family_id).await?
+            || self.is_revoked_by_logout_all(record.user_id, record.issued_at_ms).await? {
+            return Err(TokenStoreError::Revoked);
+        }
+
+        // 标记旧令牌已使用，标记失败说明并发的请求已经轮换过该令牌
+        let used_key = format!("{}{}", REFRESH_TOKEN_USED_PREFIX, token_hash);
+        if !self.set_if_absent(&used_key, "1", record.expires_at - now).await? {
+            return Err(self.reuse_detected(&record).await);
+        }
+
+        // 旧令牌的记录改为墓碑，之后再出现时即使使用标记已不存在也能识别为重用
+        let tombstone = RefreshTokenRecord { rotated: true, ..record.clone() };
+        let raw = serde_json::to_string(&tombstone).map_err(|e| TokenStoreError::Backend(e.to_string()))?;
+        self.set(&record_key, &raw, record.expires_at - now).await?;
+
+        self.issue_in_family(record.user_id, record.subject, record.family_id).await
+    }
+
+    // 已轮换的令牌被再次使用：吊销令牌族，吊销失败时返回存储错误
+    async fn reuse_detected(&self, record: &RefreshTokenRecord) -> TokenStoreError {
+        warn!("检测到刷新令牌重用，吊销令牌族: user_id={}, family={}", record.user_id, record.family_id);
+        match self.revoke_family(&record.family_id).await {
+            Ok(()) => TokenStoreError::ReuseDetected,
+            Err(e) => e,
+        }
+    }
+
+    // 吊销整个令牌族
+    pub async fn revoke_family(&self, family_id: &str) -> Result<(), TokenStoreError> {
+        let key = format!("{}{}", REFRESH_FAMILY_REVOKED_PREFIX, family_id);
+        self.set(&key, "1", self.refresh_ttl.as_secs()).await
+    }
+
+    // 查找刷新令牌并吊销其令牌族，用于登出
+    pub async fn revoke_refresh_token(&self, token: &str) -> Result<(), TokenStoreError> {
+        let key = format!("{}{}", REFRESH_TOKEN_PREFIX, hash_token(token));
+        if let Some(raw) = self.get(&key).await? {
+            let record: RefreshTokenRecord = serde_json::from_str(&raw).map_err(|e| TokenStoreError::Backend(e.to_string()))?;
+            self.revoke_family(&record.family_id).await?;
+        }
+        Ok(())
+    }
+
+    // 吊销单个访问令牌，记录保留到令牌自然过期为止
+    pub async fn revoke_access_token(&self, jti: &str, expires_at: u64) -> Result<(), TokenStoreError> {
+        let now = unix_now();
+        if expires_at <= now {
+            return Ok(());
+        }
+        self.set(&format!("{}{}", REVOKED_JTI_PREFIX, jti), "1", expires_at - now).await
+    }
+
+    // 登出用户的所有会话：此刻之前签发的访问令牌和刷新令牌全部失效
+    // 时间精确到毫秒，同一秒内稍后重新登录签发的令牌不受影响
+    // 记录保留一个刷新令牌有效期，足以覆盖所有已签发的令牌
+    pub async fn revoke_all_sessions(&self, user_id: u64) -> Result<(), TokenStoreError> {
+        let key = format!("{}{}", SESSIONS_REVOKED_BEFORE_PREFIX, user_id);
+        self.set(&key, &unix_now_millis().to_string(), self.refresh_ttl.as_secs()).await
+    }
+
+    // 检查访问令牌是否已被吊销，issued_at_ms 为令牌的签发时间（毫秒）
+    pub async fn is_access_token_revoked(&self, jti: &str, user_id: u64, issued_at_ms: u64) -> Result<bool, TokenStoreError> {
+        if self.get(&format!("{}{}", REVOKED_JTI_PREFIX, jti)).await?.is_some() {
+            return Ok(true);
+        }
+        self.is_revoked_by_logout_all(user_id, issued_at_ms).await
+    }
+
+    // 签发时间早于“登出所有会话”时间的令牌视为已吊销，记录无法解析时按出错处理
+    async fn is_revoked_by_logout_all(&self, user_id: u64, issued_at_ms: u64) -> Result<bool, TokenStoreError> {
+        let key = format!("{}{}", SESSIONS_REVOKED_BEFORE_PREFIX, user_id);
+        match self.get(&key).await? {
+            Some(raw) => raw.parse::<u64>()
+                .map(|revoked_before| issued_at_ms < revoked_before)
+                .map_err(|_| TokenStoreError::Backend(format!("无效的会话吊销时间: {}", raw))),
+            None => Ok(false),
+        }
+    }
+
+    async fn is_family_revoked(&self, family_id: &str) -> Result<bool, TokenStoreError> {
+        let key = format!("{}{}", REFRESH_FAMILY_REVOKED_PREFIX, family_id);
+        Ok(self.get(&key).await?.is_some())
+    }
+
+    async fn issue_in_family(&self, user_id: u64, subject: String, family_id: String) -> Result<IssuedRefreshToken, TokenStoreError> {
+        let token = random_token();
+        let ttl = self.refresh_ttl.as_secs();
+        let now_ms = unix_now_millis();
+        let record = RefreshTokenRecord {
+            user_id,
+            subject,
+            family_id,
+            issued_at_ms: now_ms,
+            expires_at: now_ms / 1000 + ttl,
+            rotated: false,
+        };
+        let raw = serde_json::to_string(&record).map_err(|e| TokenStoreError::Backend(e.to_string()))?;
+        self.set(&format!("{}{}", REFRESH_TOKEN_PREFIX, hash_token(&token)), &raw, ttl).await?;
+        Ok(IssuedRefreshToken { token, record })
+    }
+
+    // 获取Redis连接，未配置Redis时返回None并使用进程内缓存
+    // 配置了Redis时获取连接失败直接返回错误，不退回进程内缓存，
+    // 否则Redis故障期间吊销记录、令牌族状态和重用检测都会被忽略
+    async fn redis_connection(&self) -> Result<Option<Connection>, TokenStoreError> {
+        redis_pool::try_get_connection(&self.redis).await.transpose().map_err(TokenStoreError::Backend)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, TokenStoreError> {
+        match self.redis_connection().await? {
+            Some(mut conn) => redis_pool::get(&mut conn, key).await.map_err(|e| TokenStoreError::Backend(e.to_string())),
+            None => self.cache.get(key).map_err(TokenStoreError::Backend),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), TokenStoreError> {
+        match self.redis_connection().await? {
+            Some(mut conn) => redis_pool::set_with_expiry(&mut conn, key, value, ttl_secs)
+                .await
+                .map_err(|e| TokenStoreError::Backend(e.to_string())),
+            None => self.cache.set(key, value.to_string(), Some(ttl_secs)).map_err(|e| TokenStoreError::Backend(e.to_string())),
+        }
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, TokenStoreError> {
+        match self.redis_connection().await? {
+            Some(mut conn) => redis_pool::set_nx_with_expiry(&mut conn, key, value, ttl_secs)
+                .await
+                .map_err(|e| TokenStoreError::Backend(e.to_string())),
+            None => self.cache.set_if_absent(key, value.to_string(), Some(ttl_secs)).map_err(|e| TokenStoreError::Backend(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::cache::{CacheConfig, SimpleCache};
+    use crate::config::RedisConfig;
+    use super::*;
+
+    fn memory_store() -> TokenStore {
+        TokenStore::new(Arc::new(None), Arc::new(SimpleCache::new(CacheConfig::unbounded())), Duration::from_secs(3600))
+    }
+
+    #[tokio::test]
+    async fn logout_all_only_revokes_tokens_issued_before_it() {
+        let store = memory_store();
+        let before = store.issue_refresh_token(1, String::from("13800000000")).await.unwrap();
+        let access_issued_ms = unix_now_millis();
+        std::thread::sleep(Duration::from_millis(2));
+
+        store.revoke_all_sessions(1).await.unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        let after = store.issue_refresh_token(1, String::from("13800000000")).await.unwrap();
+
+        assert!(matches!(store.rotate_refresh_token(&before.token).await, Err(TokenStoreError::Revoked)));
+        assert!(store.rotate_refresh_token(&after.token).await.is_ok());
+        assert!(store.is_access_token_revoked("jti-before", 1, access_issued_ms).await.unwrap());
+        assert!(!store.is_access_token_revoked("jti-after", 1, unix_now_millis()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn revocations_survive_a_flood_of_other_entries() {
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        let store = TokenStore::new(Arc::new(None), cache.clone(), Duration::from_secs(3600));
+        let session = store.issue_refresh_token(1, String::from("13800000000")).await.unwrap();
+        let access_issued_ms = unix_now_millis();
+        std::thread::sleep(Duration::from_millis(2));
+        store.revoke_access_token("jti-1", unix_now() + 600).await.unwrap();
+        store.revoke_refresh_token(&session.token).await.unwrap();
+        store.revoke_all_sessions(2).await.unwrap();
+
+        // 模拟匿名请求写入的大量登录失败计数，远超默认缓存容量
+        for i in 0..50_000 {
+            cache.incr_by(&format!("login_failures:phone:{}", i), 1, Some(900)).unwrap();
+        }
+
+        assert!(store.is_access_token_revoked("jti-1", 1, unix_now_millis()).await.unwrap());
+        assert!(store.is_access_token_revoked("jti-2", 2, access_issued_ms).await.unwrap());
+        assert!(matches!(store.rotate_refresh_token(&session.token).await, Err(TokenStoreError::Revoked)));
+    }
+
+    #[tokio::test]
+    async fn replaying_a_rotated_token_revokes_the_family() {
+        let cache = Arc::new(SimpleCache::new(CacheConfig::unbounded()));
+        let store = TokenStore::new(Arc::new(None), cache.clone(), Duration::from_secs(3600));
+        let stolen = store.issue_refresh_token(1, String::from("13800000000")).await.unwrap();
+        let rotated = store.rotate_refresh_token(&stolen.token).await.unwrap();
+
+        // 即使使用标记丢失，墓碑记录仍能识别重用
+        cache.remove(&format!("{}{}", REFRESH_TOKEN_USED_PREFIX, hash_token(&stolen.token))).unwrap();
+        assert!(matches!(store.rotate_refresh_token(&stolen.token).await, Err(TokenStoreError::ReuseDetected)));
+        assert!(matches!(store.rotate_refresh_token(&rotated.token).await, Err(TokenStoreError::Revoked)));
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_fails_closed() {
+        let redis = redis_pool::init_redis_pool(&RedisConfig {
+            url: String::from("redis://127.0.0.1:1/0"),
+            ..RedisConfig::default()
+        }).unwrap();
+        let store = TokenStore::new(redis, Arc::new(SimpleCache::new(CacheConfig::default())), Duration::from_secs(3600));
+        assert!(matches!(store.is_access_token_revoked("jti", 1, unix_now_millis()).await, Err(TokenStoreError::Backend(_))));
+    }
+}
@@ -0,0 +1,115 @@
+// 简单的通配符匹配
+// `?` 匹配单个字符，`*` 匹配任意个非分隔符字符，`**` 匹配任意个字符（包括分隔符）
+// 模式可能来自请求参数，使用回溯位置迭代匹配，耗时不超过 模式长度 × 文本长度
+pub fn glob_match(pattern: &str, text: &str, separator: char) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 和 `**` 的回溯位置：(通配符之后的模式位置, 通配符匹配结束的文本位置)
+    // 遇到新的 `**` 后之前的回溯位置都不再需要，`*` 不能跨越分隔符，只需保留同一段内最近的一个
+    let mut star: Option<(usize, usize)> = None;
+    let mut globstar: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') if pattern.get(p + 1) == Some(&'*') => {
+                p += 2;
+                globstar = Some((p, t));
+                star = None;
+                continue;
+            },
+            Some('*') => {
+                p += 1;
+                star = Some((p, t));
+                continue;
+            },
+            Some('?') if text[t] != separator => {
+                p += 1;
+                t += 1;
+                continue;
+            },
+            Some(&c) if c != '?' && c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            },
+            _ => {},
+        }
+
+        // 不匹配时先让最近的 `*` 多匹配一个字符，遇到分隔符时改由最近的 `**` 多匹配一个字符
+        match (star, globstar) {
+            (Some((star_p, star_t)), _) if text[star_t] != separator => {
+                star = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            },
+            (_, Some((globstar_p, globstar_t))) => {
+                star = None;
+                globstar = Some((globstar_p, globstar_t + 1));
+                p = globstar_p;
+                t = globstar_t + 1;
+            },
+            _ => return false,
+        }
+    }
+
+    // 文本已匹配完，剩余的模式只能是通配符
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use super::*;
+
+    // 逐一尝试所有匹配方式的递归实现，耗时为指数级，只用于对照验证
+    fn reference_match(pattern: &[char], text: &[char], separator: char) -> bool {
+        match pattern.first() {
+            None => text.is_empty(),
+            Some('*') if pattern.get(1) == Some(&'*') => {
+                (0..=text.len()).any(|i| reference_match(&pattern[2..], &text[i..], separator))
+            },
+            Some('*') => {
+                let segment = text.iter().position(|&c| c == separator).unwrap_or(text.len());
+                (0..=segment).any(|i| reference_match(&pattern[1..], &text[i..], separator))
+            },
+            Some('?') => !text.is_empty() && text[0] != separator && reference_match(&pattern[1..], &text[1..], separator),
+            Some(c) => !text.is_empty() && text[0] == *c && reference_match(&pattern[1..], &text[1..], separator),
+        }
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(glob_match("/api/health/*", "/api/health/ready", '/'));
+        assert!(!glob_match("/api/health/*", "/api/health/ready/db", '/'));
+        assert!(glob_match("/api/**", "/api/health/ready/db", '/'));
+        assert!(glob_match("/api/**/db", "/api/health/ready/db", '/'));
+        assert!(glob_match("user:?", "user:1", ':'));
+        assert!(!glob_match("user:?", "user:12", ':'));
+        assert!(glob_match("user:*", "user:", ':'));
+        assert!(!glob_match("user:*", "user:1:profile", ':'));
+        assert!(glob_match("**:profile", "user:1:profile", ':'));
+    }
+
+    #[test]
+    fn agrees_with_reference_implementation() {
+        let mut rng = rand::thread_rng();
+        let pattern_chars = ['a', 'b', ':', '?', '*'];
+        let text_chars = ['a', 'b', ':'];
+        for _ in 0..20_000 {
+            let pattern: String = (0..rng.gen_range(0..8)).map(|_| pattern_chars[rng.gen_range(0..pattern_chars.len())]).collect();
+            let text: String = (0..rng.gen_range(0..8)).map(|_| text_chars[rng.gen_range(0..text_chars.len())]).collect();
+            let expected = reference_match(&pattern.chars().collect::<Vec<_>>(), &text.chars().collect::<Vec<_>>(), ':');
+            assert_eq!(glob_match(&pattern, &text, ':'), expected, "pattern={:?}, text={:?}", pattern, text);
+        }
+    }
+
+    #[test]
+    fn many_wildcards_do_not_backtrack_exponentially() {
+        let pattern = format!("{}b", "*a".repeat(50));
+        let text = "a".repeat(10_000);
+        assert!(!glob_match(&pattern, &text, ':'));
+        let pattern = format!("{}b", "**a".repeat(50));
+        assert!(!glob_match(&pattern, &text, ':'));
+    }
+}
@@ -2,6 +2,7 @@ use crate::middleware::{JsonLogger, JsonLoggerConfig, LogLevel};
 use serde_json::json;
 
 // JSON日志器示例
+#[allow(dead_code)]
 pub fn demonstrate_json_logger() {
     // 创建默认配置的日志器
     let mut default_logger = JsonLogger::new(JsonLoggerConfig::default()).expect("Failed to create default logger");
@@ -1,2 +1,5 @@
 // 导出工具模块
-pub mod logger_example;
\ No newline at end of file
+pub mod logger_example;
+pub mod glob;
+pub mod time;
+pub mod token;
@@ -0,0 +1,17 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 当前UNIX时间戳（秒）
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+// 当前UNIX时间戳（毫秒）
+pub fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}